@@ -1,5 +1,7 @@
 use clap::{Parser, Subcommand};
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
+
+use crate::core::utils::parse_duration;
 
 #[derive(Parser, Debug)]
 #[command(
@@ -7,7 +9,7 @@ use std::path::PathBuf;
     version = env!("CARGO_PKG_VERSION")
 )]
 pub struct Args {
-    #[arg(short, long, value_name = "FILE")]
+    #[arg(short, long, value_name = "FILE", help = "Load this config file instead of searching the default locations (also $STASIS_CONFIG)")]
     pub config: Option<PathBuf>,
 
     #[arg(short, long, action)]
@@ -33,19 +35,78 @@ pub enum Command {
 
     #[command(about = "Manually trigger a specific idle action by name")]
     Trigger { 
-        #[arg(help = "Action name to trigger (e.g., 'brightness', 'dpms', 'lock_screen', 'pre_suspend', 'suspend')")]
+        #[arg(help = "Action name to trigger (e.g., 'brightness', 'keyboard_backlight', 'dpms', 'lock_screen', 'pre_suspend', 'suspend')")]
         step: String,
     },
 
+    #[command(about = "Switch between named profiles from the config")]
+    Profile {
+        #[command(subcommand)]
+        action: ProfileCommand,
+    },
+
     #[command(about = "Toggle manual idle inhibition (for status bars such as Waybar)")]
     ToggleInhibit,
 
+    #[command(about = "Inhibit idle until `uninhibit`, or for a while with --for")]
+    Inhibit {
+        #[arg(long = "for", value_name = "DURATION", value_parser = parse_inhibit_duration, help = "Release automatically after this long, e.g. 45m or 1h30m")]
+        duration: Option<Duration>,
+
+        #[arg(long, help = "Name the inhibit, so several can be held and released separately")]
+        reason: Option<String>,
+    },
+
+    #[command(about = "Release a named manual inhibit, or all of them")]
+    Uninhibit {
+        #[arg(help = "Reason given to `stasis inhibit`")]
+        reason: Option<String>,
+    },
+
     #[command(about = "Stop the currently running instances of Stasis")]
     Stop,
 
+    #[command(about = "Validate a config file without starting the daemon")]
+    Check {
+        #[arg(value_name = "FILE", help = "Config file to check (defaults to the normal config search)")]
+        file: Option<PathBuf>,
+
+        #[arg(long, value_parser = ["laptop", "desktop"], help = "Check as this chassis type instead of the detected one")]
+        chassis: Option<String>,
+    },
+
+    #[command(about = "List what is inhibiting idle right now, and which actions it blocks")]
+    Inhibitors {
+        #[arg(long, help = "Output as JSON")]
+        json: bool,
+    },
+
     #[command(about = "Display durrent session information")]
     Info {
         #[arg(long, help = "Output as JSON (for Waybar or scripts)")]
         json: bool,
     },
 }
+
+#[derive(Subcommand, Debug)]
+pub enum ProfileCommand {
+    #[command(about = "Activate a profile by name")]
+    Set {
+        #[arg(help = "Profile name as defined under `profiles:` in the config")]
+        name: String,
+    },
+
+    #[command(about = "List configured profiles (the active one is marked with *)")]
+    List,
+
+    #[command(about = "Leave the active profile and return to the default actions")]
+    Reset,
+}
+
+fn parse_inhibit_duration(input: &str) -> Result<Duration, String> {
+    match parse_duration(input) {
+        Some(duration) if !duration.is_zero() => Ok(duration),
+        Some(_) => Err("duration must be greater than zero".to_string()),
+        None => Err(format!("invalid duration '{}', use e.g. 90s, 45m or 1h30m", input)),
+    }
+}
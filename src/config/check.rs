@@ -0,0 +1,83 @@
+use std::{fmt::{Display, Formatter, Result}, path::PathBuf};
+
+use crate::{
+    config::{parser::parse_config, ConfigSource},
+    core::utils::ChassisKind,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// A problem found while parsing the config, with its location when known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigDiagnostic {
+    pub severity: Severity,
+    pub key: String,
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+}
+
+impl Display for ConfigDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match (&self.file, self.line) {
+            (Some(file), Some(line)) => write!(f, "{}:{}: ", file.display(), line)?,
+            (Some(file), None) => write!(f, "{}: ", file.display())?,
+            _ => {}
+        }
+        write!(f, "{}: {}: {}", self.severity, self.key, self.message)
+    }
+}
+
+/// Parse the config without starting the daemon and print every diagnostic.
+/// Returns the process exit code: non-zero if anything was reported.
+pub fn run_check(source: &ConfigSource, chassis: ChassisKind) -> i32 {
+    let mut diagnostics = Vec::new();
+    let result = parse_config(source, chassis, &mut diagnostics);
+
+    for diagnostic in &diagnostics {
+        println!("{}", diagnostic);
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.severity == Severity::Error).count();
+    let warnings = diagnostics.len() - errors;
+
+    match result {
+        Ok(cfg) => {
+            let files = if cfg.config_layers.is_empty() {
+                "(built-in defaults)".to_string()
+            } else {
+                cfg.config_layers
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            println!(
+                "Checked {} ({} actions): {} error(s), {} warning(s)",
+                files,
+                cfg.actions.len(),
+                errors,
+                warnings
+            );
+            if diagnostics.is_empty() { 0 } else { 1 }
+        }
+        Err(e) => {
+            println!("error: {:#}", e);
+            println!("Config is invalid: {} error(s), {} warning(s)", errors + 1, warnings);
+            1
+        }
+    }
+}
@@ -1,33 +1,100 @@
 use std::{collections::BTreeSet, time::Duration};
-use crate::{config::model::StasisConfig, core::utils};
+use crate::{
+    config::{layers::layer_label, model::{InhibitScope, StasisConfig}},
+    core::{manager::state::{BatteryLevel, Inhibitor, LogindInhibitor}, utils},
+};
+
+/// Runtime state shown above the config by `stasis info`
+#[derive(Debug, Default)]
+pub struct InfoStatus<'a> {
+    pub idle_time: Option<Duration>,
+    pub uptime: Option<Duration>,
+    pub is_inhibited: Option<bool>,
+    pub is_manually_inhibited: Option<bool>,
+    pub active_block: Option<&'a str>,
+    pub active_profile: Option<&'a str>,
+    pub active_schedule: &'a [String],
+    pub battery: Option<(u8, BatteryLevel)>,
+    pub logind_inhibitors: &'a [LogindInhibitor],
+    pub wayland_inhibited: Option<bool>,
+    pub inhibitors: &'a [(String, Inhibitor)],
+}
 
 impl StasisConfig {
-    pub fn pretty_print(
-        &self,
-        idle_time: Option<Duration>,
-        uptime: Option<Duration>,
-        is_inhibited: Option<bool>,
-        is_manually_inhibited: Option<bool>,
-    ) -> String {
+    pub fn pretty_print(&self, status: &InfoStatus) -> String {
+        let active_profile = status.active_profile;
+        let active_schedule = status.active_schedule;
         let mut out = String::new();
 
         out.push_str("Status:\n");
 
-        if let Some(idle) = idle_time {
+        if let Some(idle) = status.idle_time {
             out.push_str(&format!("  IdleTime           = {}\n", utils::format_duration(idle)));
         }
-        if let Some(up) = uptime {
+        if let Some(up) = status.uptime {
             out.push_str(&format!("  Uptime             = {}\n", utils::format_duration(up)));
         }
-        if let Some(inhibited) = is_inhibited {
+        if let Some(inhibited) = status.is_inhibited {
             out.push_str(&format!("  IdleInhibited      = {}\n", inhibited));
         }
-        if let Some(inhibited) = is_manually_inhibited {
+        if let Some(inhibited) = status.is_manually_inhibited {
             out.push_str(&format!("  ManuallyInhibited  = {}\n", inhibited));
         }
+        if let Some(inhibited) = status.wayland_inhibited.filter(|_| self.respect_wayland_inhibitors) {
+            out.push_str(&format!("  WaylandInhibited   = {}\n", inhibited));
+        }
+        if let Some(block) = status.active_block {
+            out.push_str(&format!("  ActiveBlock        = {}\n", block));
+        }
+        if let Some((capacity, level)) = status.battery {
+            out.push_str(&format!("  Battery            = {}% ({})\n", capacity, level));
+        }
+        if !status.logind_inhibitors.is_empty() {
+            let ignored = if self.respect_logind_inhibitors { "" } else { " (ignored)" };
+            for (i, inhibitor) in status.logind_inhibitors.iter().enumerate() {
+                let label = if i == 0 { "LogindInhibitors" } else { "" };
+                out.push_str(&format!(
+                    "  {:<18} = {} (pid {}): {}{}\n",
+                    label, inhibitor.who, inhibitor.pid, inhibitor.why, ignored
+                ));
+            }
+        }
+        for (i, (source, inhibitor)) in status.inhibitors.iter().enumerate() {
+            let label = if i == 0 { "Inhibitors" } else { "" };
+            out.push_str(&format!(
+                "  {:<18} = {}: {} (blocks {}, for {}{})\n",
+                label,
+                source,
+                inhibitor.reason,
+                inhibitor.scope,
+                utils::format_duration(inhibitor.since.elapsed().unwrap_or_default()),
+                inhibitor.remaining()
+                    .map(|d| format!(", {} left", utils::format_duration(d)))
+                    .unwrap_or_default()
+            ));
+        }
+        if !self.profiles.is_empty() {
+            out.push_str(&format!("  ActiveProfile      = {}\n", active_profile.unwrap_or("-")));
+        }
+        if !self.schedule.is_empty() {
+            let active = if active_schedule.is_empty() { "-".to_string() } else { active_schedule.join(",") };
+            out.push_str(&format!("  ActiveSchedule     = {}\n", active));
+        }
 
         // General settings
         out.push_str("\nConfig:\n");
+        out.push_str(&format!(
+            "  ConfigFile         = {}\n",
+            layer_label(self.config_path.as_deref())
+        ));
+        if self.config_layers.len() > 1 {
+            let layers = self.config_layers
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            out.push_str(&format!("  ConfigLayers       = {}\n", layers));
+        }
         out.push_str(&format!(
             "  PreSuspendCommand  = {}\n",
             self.pre_suspend_command.as_deref().unwrap_or("-")
@@ -41,9 +108,20 @@ impl StasisConfig {
             "  RespectInhibitors  = {}\n",
             if self.respect_wayland_inhibitors { "true" } else { "false" }
         ));
-        out.push_str(&format!("  DebounceSeconds    = {}\n", self.debounce_seconds));
+        out.push_str(&format!("  RespectLogind      = {}\n", self.respect_logind_inhibitors));
+        out.push_str(&format!("  ExitWithCompositor = {}\n", self.exit_with_compositor));
+        out.push_str(&format!(
+            "  DebounceSeconds    = {}\n",
+            utils::format_duration(Duration::from_secs(self.debounce_seconds as u64))
+        ));
         out.push_str(&format!("  LidCloseAction     = {}\n", self.lid_close_action));
         out.push_str(&format!("  LidOpenAction      = {}\n", self.lid_open_action));
+        if self.actions.iter().any(|a| a.name.starts_with("battery_low.") || a.name.starts_with("battery_critical.")) {
+            out.push_str(&format!(
+                "  BatteryThresholds  = low {}%, critical {}% (hysteresis {}%)\n",
+                self.battery_low_threshold, self.battery_critical_threshold, self.battery_hysteresis
+            ));
+        }
 
         let apps = if self.inhibit_apps.is_empty() {
             "-".to_string()
@@ -55,6 +133,15 @@ impl StasisConfig {
                 .join(",")
         };
         out.push_str(&format!("  InhibitApps        = {}\n", apps));
+        out.push_str(&format!("  InhibitFullscreen  = {}\n", self.inhibit_on_fullscreen));
+        let scopes: Vec<String> = self.inhibit_scopes.entries()
+            .iter()
+            .filter(|(_, scope)| **scope != InhibitScope::All)
+            .map(|(source, scope)| format!("{}={}", source, scope))
+            .collect();
+        if !scopes.is_empty() {
+            out.push_str(&format!("  InhibitScopes      = {}\n", scopes.join(" ")));
+        }
 
 
 
@@ -66,6 +153,10 @@ impl StasisConfig {
         for action in &self.actions {
             let group = if action.name.starts_with("ac.") {
                 "AC"
+            } else if action.name.starts_with("battery_low.") {
+                "Battery Low"
+            } else if action.name.starts_with("battery_critical.") {
+                "Battery Critical"
             } else if action.name.starts_with("battery.") {
                 "Battery"
             } else {
@@ -78,11 +169,11 @@ impl StasisConfig {
             }
 
             out.push_str(&format!(
-                "    {:<20} Timeout={} Kind={} Command=\"{}\"",
+                "    {:<20} Timeout={} Kind={} Command={}",
                 action.name,
-                action.timeout,
+                utils::format_duration(Duration::from_secs(action.timeout)),
                 action.kind,
-                action.command
+                action.command_label()
             ));
 
             if let Some(resume_cmd) = &action.resume_command {
@@ -92,6 +183,74 @@ impl StasisConfig {
             out.push('\n');
         }
 
+        // Profiles
+        if !self.profiles.is_empty() {
+            out.push_str("\nProfiles:\n");
+            for profile in &self.profiles {
+                let marker = if active_profile == Some(profile.name.as_str()) { " (active)" } else { "" };
+                out.push_str(&format!("  [{}]{}\n", profile.name, marker));
+                out.push_str(&format!("    Inhibit            = {}\n", profile.inhibit));
+                if let Some(media) = profile.monitor_media {
+                    out.push_str(&format!("    MonitorMedia       = {}\n", media));
+                }
+                if let Some(apps) = &profile.inhibit_apps {
+                    let apps = apps.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(",");
+                    out.push_str(&format!("    InhibitApps        = {}\n", if apps.is_empty() { "-" } else { &apps }));
+                }
+                for action in &profile.actions {
+                    out.push_str(&format!(
+                        "    {:<20} Timeout={} Kind={} Command={}\n",
+                        action.name,
+                        utils::format_duration(Duration::from_secs(action.timeout)),
+                        action.kind,
+                        action.command_label()
+                    ));
+                }
+            }
+        }
+
+        // Inhibit rules
+        if !self.inhibit_rules.is_empty() {
+            out.push_str("\nInhibitRules:\n");
+            for rule in &self.inhibit_rules {
+                out.push_str(&format!("  {:<20} {} (blocks {})\n", rule.name, rule.matchers(), rule.scope));
+            }
+        }
+
+        // Schedule
+        if !self.schedule.is_empty() {
+            out.push_str("\nSchedule:\n");
+            for rule in &self.schedule {
+                let mut effects = Vec::new();
+                if rule.inhibit {
+                    effects.push("inhibit".to_string());
+                }
+                if let Some(profile) = &rule.profile {
+                    effects.push(format!("profile={}", profile));
+                }
+                if let Some(block) = &rule.block {
+                    effects.push(format!("block={}", block));
+                }
+                let marker = if active_schedule.contains(&rule.name) { " (active)" } else { "" };
+                out.push_str(&format!(
+                    "  {:<20} {} {}{}\n",
+                    rule.name,
+                    rule.window(),
+                    effects.join(" "),
+                    marker
+                ));
+            }
+        }
+
+        // Where each effective value came from (only interesting with several layers)
+        if self.config_layers.len() > 1 {
+            out.push_str("\nSources:\n");
+            for (key, path) in &self.value_sources {
+                let key = key.strip_prefix("stasis.").unwrap_or(key);
+                out.push_str(&format!("  {:<40} <- {}\n", key, layer_label(path.as_deref())));
+            }
+        }
+
         out
     }
 }
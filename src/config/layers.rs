@@ -0,0 +1,190 @@
+use std::{cell::RefCell, fs, path::{Path, PathBuf}};
+use rune_cfg::{RuneConfig, RuneError, Value};
+
+use crate::config::check::{ConfigDiagnostic, Severity};
+
+/// A single parsed config file (or the embedded defaults when `path` is None)
+pub struct ConfigLayer {
+    pub path: Option<PathBuf>,
+    pub config: RuneConfig,
+}
+
+/// Stack of config layers, lowest priority first.
+///
+/// Lookups resolve each key against the highest layer that defines it, so a
+/// later file overrides individual settings and action fields instead of
+/// replacing the whole configuration. Object keys (action blocks) are the union
+/// of all layers, in order of first appearance.
+pub struct LayeredConfig {
+    layers: Vec<ConfigLayer>,
+    sources: RefCell<Vec<(String, Option<PathBuf>)>>,
+}
+
+/// Spellings tried for a path: as written, then with `_` and `-` separators
+fn path_variants(path: &str) -> [String; 3] {
+    [path.to_string(), path.replace('-', "_"), path.replace('_', "-")]
+}
+
+fn not_found(path: &str) -> RuneError {
+    RuneError::SyntaxError {
+        message: format!("Path '{}' not found in configuration", path),
+        line: 0,
+        column: 0,
+        hint: Some("Check that the path exists in your config file".into()),
+        code: Some(304),
+    }
+}
+
+impl LayeredConfig {
+    pub fn new(layers: Vec<ConfigLayer>) -> Self {
+        Self {
+            layers,
+            sources: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Files that contributed to this config, lowest priority first
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.layers.iter().filter_map(|l| l.path.clone()).collect()
+    }
+
+    /// Highest priority file, if any layer came from disk
+    pub fn top_path(&self) -> Option<PathBuf> {
+        self.layers.last().and_then(|l| l.path.clone())
+    }
+
+    /// Find the highest layer defining `path` and the spelling it uses
+    fn resolve(&self, path: &str) -> Option<(&ConfigLayer, String)> {
+        self.layers.iter().rev().find_map(|layer| {
+            path_variants(path)
+                .into_iter()
+                .find(|p| layer.config.has(p))
+                .map(|p| (layer, p))
+        })
+    }
+
+    fn record_source(&self, path: &str, layer: &ConfigLayer) {
+        let mut sources = self.sources.borrow_mut();
+        if !sources.iter().any(|(p, _)| p == path) {
+            sources.push((path.to_string(), layer.path.clone()));
+        }
+    }
+
+    pub fn get<T>(&self, path: &str) -> Result<T, RuneError>
+    where
+        T: TryFrom<Value, Error = RuneError>,
+    {
+        let (layer, resolved) = self.resolve(path).ok_or_else(|| not_found(path))?;
+        // Convert here rather than via RuneConfig::get, whose line lookup can
+        // point at a same-named key in another block; diagnostics locate it instead
+        let value = T::try_from(layer.config.get_value(&resolved)?)?;
+        self.record_source(path, layer);
+        Ok(value)
+    }
+
+    pub fn get_value(&self, path: &str) -> Result<Value, RuneError> {
+        let (layer, resolved) = self.resolve(path).ok_or_else(|| not_found(path))?;
+        let value = layer.config.get_value(&resolved)?;
+        self.record_source(path, layer);
+        Ok(value)
+    }
+
+    /// Union of the keys every layer defines under `path`
+    pub fn get_keys(&self, path: &str) -> Result<Vec<String>, RuneError> {
+        let mut keys: Vec<String> = Vec::new();
+        let mut found = false;
+
+        for layer in &self.layers {
+            let layer_keys = path_variants(path)
+                .iter()
+                .find_map(|p| layer.config.get_keys(p).ok());
+
+            if let Some(layer_keys) = layer_keys {
+                found = true;
+                for key in layer_keys {
+                    let normalized = key.replace('-', "_");
+                    if !keys.iter().any(|k| k.replace('-', "_") == normalized) {
+                        keys.push(key);
+                    }
+                }
+            }
+        }
+
+        if found { Ok(keys) } else { Err(not_found(path)) }
+    }
+
+    /// File and line defining `path`, falling back to its closest parent block
+    pub fn locate(&self, path: &str) -> (Option<PathBuf>, Option<usize>) {
+        let mut current = path;
+        loop {
+            if let Some((layer, resolved)) = self.resolve(current) {
+                let line = layer.path.as_ref().and_then(|p| {
+                    fs::read_to_string(p).ok().and_then(|content| find_line(&content, &resolved))
+                });
+                return (layer.path.clone(), line);
+            }
+            match current.rsplit_once('.') {
+                Some((parent, _)) => current = parent,
+                None => return (self.top_path(), None),
+            }
+        }
+    }
+
+    /// Build a diagnostic for `path`, located in the layer that defines it
+    pub fn diagnostic(&self, severity: Severity, path: &str, message: impl Into<String>) -> ConfigDiagnostic {
+        let (file, line) = self.locate(path);
+        ConfigDiagnostic {
+            severity,
+            key: path.to_string(),
+            message: message.into(),
+            file,
+            line,
+        }
+    }
+
+    /// Layer each value read so far came from, in read order
+    pub fn into_sources(self) -> Vec<(String, Option<PathBuf>)> {
+        self.sources.into_inner()
+    }
+}
+
+/// Line number of `path` in `content`, following `block:` / `end` scopes
+fn find_line(content: &str, path: &str) -> Option<usize> {
+    let target: Vec<String> = path.split('.').map(|s| s.replace('-', "_")).collect();
+    let mut scope: Vec<String> = Vec::new();
+
+    for (idx, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('@') {
+            continue;
+        }
+        if trimmed == "end" {
+            scope.pop();
+            continue;
+        }
+
+        let (key, is_block) = match trimmed.strip_suffix(':') {
+            Some(block) => (block.trim(), true),
+            None => (
+                trimmed.split(|c: char| c.is_whitespace() || c == '=').next().unwrap_or(""),
+                false,
+            ),
+        };
+        let key = key.replace('-', "_");
+
+        if scope.len() + 1 == target.len() && scope.iter().chain([&key]).eq(target.iter()) {
+            return Some(idx + 1);
+        }
+        if is_block {
+            scope.push(key);
+        }
+    }
+
+    None
+}
+
+/// Display label for a layer path
+pub fn layer_label(path: Option<&Path>) -> String {
+    path.map(|p| p.display().to_string())
+        .unwrap_or_else(|| "(built-in defaults)".to_string())
+}
@@ -1,21 +1,67 @@
-use std::{path::PathBuf};
+use std::{env, path::PathBuf};
 use eyre::Result;
 
+pub mod check;
 pub mod info;
+pub mod layers;
 pub mod model;
 pub mod parser;
 
-/// Determine default config path
-pub async fn get_config_path() -> Result<PathBuf> {
-    if let Some(mut path) = dirs::home_dir() {
-        path.push(".config/stasis/stasis.rune");
-        if path.exists() {
-            return Ok(path);
+/// Shipped and system-wide config locations, lowest priority first
+pub const SYSTEM_CONFIG_PATHS: &[&str] = &[
+    "/usr/share/stasis/stasis.rune",
+    "/etc/stasis/stasis.rune",
+];
+
+/// Where the configuration is loaded from
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ConfigSource {
+    /// Search the system and user locations
+    #[default]
+    Search,
+    /// Load exactly this file (`--config` or `$STASIS_CONFIG`)
+    Explicit(PathBuf),
+}
+
+impl ConfigSource {
+    /// Resolve the source from the `--config` flag, falling back to `$STASIS_CONFIG`
+    pub fn from_cli(path: Option<PathBuf>) -> Self {
+        let path = path.or_else(|| {
+            env::var_os("STASIS_CONFIG")
+                .filter(|p| !p.is_empty())
+                .map(PathBuf::from)
+        });
+
+        match path {
+            // Canonicalize so a relative path keeps working for `reload`
+            Some(p) => ConfigSource::Explicit(std::fs::canonicalize(&p).unwrap_or(p)),
+            None => ConfigSource::Search,
         }
     }
-    let fallback = PathBuf::from("/etc/stasis/stasis.rune");
-    if fallback.exists() {
-        return Ok(fallback);
+}
+
+/// User config path, honouring `$XDG_CONFIG_HOME`
+pub fn user_config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("stasis/stasis.rune"))
+}
+
+/// Every file considered for `source`, lowest priority first
+pub fn config_search_paths(source: &ConfigSource) -> Vec<PathBuf> {
+    match source {
+        ConfigSource::Explicit(path) => vec![path.clone()],
+        ConfigSource::Search => {
+            let mut paths: Vec<PathBuf> = SYSTEM_CONFIG_PATHS.iter().map(PathBuf::from).collect();
+            paths.extend(user_config_path());
+            paths
+        }
     }
-    Err(eyre::eyre!("Could not find stasis configuration file"))
+}
+
+/// Determine the highest priority config file that exists
+pub fn get_config_path(source: &ConfigSource) -> Result<PathBuf> {
+    config_search_paths(source)
+        .into_iter()
+        .rev()
+        .find(|path| path.exists())
+        .ok_or_else(|| eyre::eyre!("Could not find stasis configuration file"))
 }
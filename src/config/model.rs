@@ -1,22 +1,63 @@
-use std::{fmt::{Display, Formatter, Result}, time::Instant};
+use std::{fmt::{Display, Formatter, Result}, path::PathBuf, time::{Duration, Instant}};
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDateTime, NaiveTime, Weekday};
 use regex::Regex;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum IdleAction {
     Brightness,
+    KeyboardBacklight,
     Dpms,
     LockScreen,
     Suspend,
+    Hibernate,
+    HybridSleep,
+    SuspendThenHibernate,
     Custom,
 }
 
+impl IdleAction {
+    /// Kinds that put the machine to sleep
+    pub fn is_sleep(&self) -> bool {
+        self.logind_method().is_some()
+    }
+
+    /// Kinds that work without a command
+    pub fn has_native(&self) -> bool {
+        self.is_sleep() || matches!(self, IdleAction::Dpms | IdleAction::KeyboardBacklight)
+    }
+
+    /// Sysfs class (and logind subsystem) of the lights this kind dims
+    pub fn light_subsystem(&self) -> Option<&'static str> {
+        match self {
+            IdleAction::Brightness => Some("backlight"),
+            IdleAction::KeyboardBacklight => Some("leds"),
+            _ => None,
+        }
+    }
+
+    /// org.freedesktop.login1.Manager method performing this kind natively
+    pub fn logind_method(&self) -> Option<&'static str> {
+        match self {
+            IdleAction::Suspend => Some("Suspend"),
+            IdleAction::Hibernate => Some("Hibernate"),
+            IdleAction::HybridSleep => Some("HybridSleep"),
+            IdleAction::SuspendThenHibernate => Some("SuspendThenHibernate"),
+            _ => None,
+        }
+    }
+}
+
 impl Display for IdleAction {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
             IdleAction::Brightness => write!(f, "brightness"),
+            IdleAction::KeyboardBacklight => write!(f, "keyboard_backlight"),
             IdleAction::Dpms => write!(f, "dpms"),
             IdleAction::LockScreen => write!(f, "lock_screen"),
             IdleAction::Suspend => write!(f, "suspend"),
+            IdleAction::Hibernate => write!(f, "hibernate"),
+            IdleAction::HybridSleep => write!(f, "hybrid_sleep"),
+            IdleAction::SuspendThenHibernate => write!(f, "suspend_then_hibernate"),
             IdleAction::Custom => write!(f, "custom"),
         }
     }
@@ -30,7 +71,21 @@ pub struct IdleActionBlock {
     pub kind: IdleAction,
     pub resume_command: Option<String>,
     pub lock_command: Option<String>,
+    pub backlight: Option<BacklightTarget>,
     pub last_triggered: Option<Instant>,
+    /// Came due while a scoped inhibitor blocked it; runs once that lifts
+    pub skipped: bool,
+}
+
+/// Native dimming of a brightness or keyboard_backlight action
+#[derive(Debug, Clone, PartialEq)]
+pub struct BacklightTarget {
+    /// Device under /sys/class/backlight (or leds); every device when unset
+    pub device: Option<String>,
+    /// Target level in percent of each device's max_brightness
+    pub level: u8,
+    /// How long to fade from the current level to `level`
+    pub fade: Duration,
 }
 
 impl IdleActionBlock {
@@ -38,6 +93,36 @@ impl IdleActionBlock {
         self.timeout == 0
     }
     
+    /// Sleep actions without a command are performed through logind
+    pub fn is_native(&self) -> bool {
+        self.kind.is_sleep() && self.command.trim().is_empty()
+    }
+
+    /// Command for display, or how the action runs when it has none
+    pub fn command_label(&self) -> String {
+        if self.kind == IdleAction::Dpms {
+            if self.command.trim().is_empty() {
+                "(wlr-output-power)".to_string()
+            } else {
+                format!("(wlr-output-power, fallback \"{}\")", self.command)
+            }
+        } else if let Some(target) = &self.backlight {
+            let mut label = format!("({} {}%", self.kind.light_subsystem().unwrap_or("backlight"), target.level);
+            if let Some(device) = &target.device {
+                label.push_str(&format!(" on {}", device));
+            }
+            if !target.fade.is_zero() {
+                label.push_str(&format!(", fade {}s", target.fade.as_secs()));
+            }
+            label.push(')');
+            label
+        } else if self.is_native() {
+            format!("(logind {})", self.kind.logind_method().unwrap_or_default())
+        } else {
+            format!("\"{}\"", self.command)
+        }
+    }
+
     pub fn has_resume_command(&self) -> bool {
         self.resume_command.is_some()
     }
@@ -57,6 +142,24 @@ pub enum AppInhibitPattern {
     Regex(Regex),
 }
 
+impl AppInhibitPattern {
+    /// Whole-value match, case-insensitive for literals
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            AppInhibitPattern::Literal(s) => s.eq_ignore_ascii_case(text),
+            AppInhibitPattern::Regex(r) => r.is_match(text),
+        }
+    }
+
+    /// Substring match, case-insensitive for literals
+    pub fn is_found_in(&self, text: &str) -> bool {
+        match self {
+            AppInhibitPattern::Literal(s) => text.to_lowercase().contains(&s.to_lowercase()),
+            AppInhibitPattern::Regex(r) => r.is_match(text),
+        }
+    }
+}
+
 impl Display for AppInhibitPattern {
     fn fmt(&self, f: &mut Formatter<'_>) -> Result {
         match self {
@@ -66,6 +169,112 @@ impl Display for AppInhibitPattern {
     }
 }
 
+/// Which idle actions an inhibitor holds back
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum InhibitScope {
+    /// Every action; pauses the idle timers
+    #[default]
+    All,
+    /// Action kinds (`dpms`, `lock_screen`, ...) or action names
+    Only(Vec<String>),
+}
+
+impl InhibitScope {
+    pub fn blocks(&self, action: &IdleActionBlock) -> bool {
+        match self {
+            InhibitScope::All => true,
+            InhibitScope::Only(names) => {
+                let kind = action.kind.to_string();
+                // Names carry their block prefix ("ac.dpms")
+                let name = action.name.rsplit('.').next().unwrap_or(&action.name).replace('-', "_");
+                names.iter().any(|n| *n == kind || *n == name)
+            }
+        }
+    }
+}
+
+impl Display for InhibitScope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        match self {
+            InhibitScope::All => write!(f, "all"),
+            InhibitScope::Only(names) => write!(f, "{}", names.join(",")),
+        }
+    }
+}
+
+/// A named entry of `inhibit_rules`; every matcher that is set has to match
+#[derive(Debug, Clone)]
+pub struct AppInhibitRule {
+    pub name: String,
+    pub app_id: Option<AppInhibitPattern>,
+    pub title: Option<AppInhibitPattern>,
+    /// Full process command line, e.g. `python train.py`
+    pub cmdline: Option<AppInhibitPattern>,
+    pub workspace: Option<AppInhibitPattern>,
+    pub output: Option<AppInhibitPattern>,
+    pub scope: InhibitScope,
+}
+
+impl AppInhibitRule {
+    /// Rules with only a cmdline match processes, everything else matches windows
+    pub fn needs_window(&self) -> bool {
+        self.app_id.is_some() || self.title.is_some() || self.workspace.is_some() || self.output.is_some()
+    }
+
+    /// The matchers, e.g. `app_id=zoom title=(regex) Meeting.*`
+    pub fn matchers(&self) -> String {
+        [
+            ("app_id", &self.app_id),
+            ("title", &self.title),
+            ("cmdline", &self.cmdline),
+            ("workspace", &self.workspace),
+            ("output", &self.output),
+        ]
+        .iter()
+        .filter_map(|(field, pattern)| pattern.as_ref().map(|p| format!("{}={}", field, p)))
+        .collect::<Vec<_>>()
+        .join(" ")
+    }
+}
+
+/// The actions each built-in inhibitor source holds back
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InhibitScopes {
+    pub media: InhibitScope,
+    /// inhibit_apps
+    pub apps: InhibitScope,
+    pub fullscreen: InhibitScope,
+    /// logind `idle` block locks, e.g. from systemd-inhibit
+    pub logind: InhibitScope,
+    /// org.freedesktop.ScreenSaver clients
+    pub screensaver: InhibitScope,
+}
+
+impl InhibitScopes {
+    pub fn entries(&self) -> [(&'static str, &InhibitScope); 5] {
+        [
+            ("media", &self.media),
+            ("apps", &self.apps),
+            ("fullscreen", &self.fullscreen),
+            ("logind", &self.logind),
+            ("screensaver", &self.screensaver),
+        ]
+    }
+}
+
+/// Which fullscreen windows hold an inhibitor
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FullscreenInhibit {
+    #[default]
+    Off,
+    /// Any fullscreen window
+    Any,
+    /// Only a fullscreen window on the output that has focus
+    FocusedOutput,
+    /// Only fullscreen windows matching inhibit_apps
+    Apps,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum LidCloseAction {
     Ignore,
@@ -81,20 +290,123 @@ pub enum LidOpenAction {
     Custom(String),
 }
 
+/// A named set of actions and inhibit settings that can be switched to at runtime
+#[derive(Debug, Clone)]
+pub struct Profile {
+    pub name: String,
+    /// Replaces the default/ac/battery actions while active (unless empty)
+    pub actions: Vec<IdleActionBlock>,
+    /// Hold an inhibitor for as long as the profile is active
+    pub inhibit: bool,
+    pub monitor_media: Option<bool>,
+    pub inhibit_apps: Option<Vec<AppInhibitPattern>>,
+}
+
+/// A weekly time window that inhibits idle and/or selects a profile or block
+#[derive(Debug, Clone)]
+pub struct ScheduleRule {
+    pub name: String,
+    /// Indexed by days from Monday
+    pub days: [bool; 7],
+    pub start: NaiveTime,
+    /// Before `start` means the window runs past midnight
+    pub end: NaiveTime,
+    pub inhibit: bool,
+    pub profile: Option<String>,
+    pub block: Option<String>,
+}
+
+impl ScheduleRule {
+    fn runs_on(&self, day: Weekday) -> bool {
+        self.days[day.num_days_from_monday() as usize]
+    }
+
+    pub fn is_active_at(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+        let day = now.weekday();
+
+        if self.start < self.end {
+            self.runs_on(day) && time >= self.start && time < self.end
+        } else if self.start > self.end {
+            // Overnight window: started today, or started yesterday and not over yet
+            (self.runs_on(day) && time >= self.start) || (self.runs_on(day.pred()) && time < self.end)
+        } else {
+            self.runs_on(day)
+        }
+    }
+
+    /// Next time after `now` at which this rule may start or stop applying
+    pub fn next_boundary(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        (0..=7)
+            .flat_map(|offset| {
+                let date = now.date() + ChronoDuration::days(offset);
+                [date.and_time(self.start), date.and_time(self.end)]
+            })
+            .filter(|t| *t > now)
+            .min()
+    }
+
+    /// Days and window in config notation, e.g. "mon,tue 09:00-17:00"
+    pub fn window(&self) -> String {
+        const NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+        let days = if self.days.iter().all(|d| *d) {
+            "daily".to_string()
+        } else {
+            NAMES
+                .iter()
+                .zip(self.days)
+                .filter(|(_, on)| *on)
+                .map(|(name, _)| *name)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
+        format!("{} {}-{}", days, self.start.format("%H:%M"), self.end.format("%H:%M"))
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct StasisConfig {
     pub actions: Vec<IdleActionBlock>,
+    pub profiles: Vec<Profile>,
+    pub schedule: Vec<ScheduleRule>,
+    pub config_path: Option<PathBuf>,
+    pub config_layers: Vec<PathBuf>,
+    pub value_sources: Vec<(String, Option<PathBuf>)>,
     pub debounce_seconds: u8,
     pub inhibit_apps: Vec<AppInhibitPattern>,
+    pub inhibit_on_fullscreen: FullscreenInhibit,
+    pub inhibit_rules: Vec<AppInhibitRule>,
+    pub inhibit_scopes: InhibitScopes,
     pub monitor_media: bool,
     pub ignore_remote_media: bool,
     pub media_blacklist: Vec<String>,
     pub pre_suspend_command: Option<String>,
     pub respect_wayland_inhibitors: bool,
+    /// Treat systemd-logind `idle` block locks as inhibitors
+    pub respect_logind_inhibitors: bool,
+    /// Exit when the compositor goes away instead of waiting for it to return
+    pub exit_with_compositor: bool,
+    /// Battery percentage at or below which `on_battery_low` applies
+    pub battery_low_threshold: u8,
+    /// Battery percentage at or below which `on_battery_critical` applies
+    pub battery_critical_threshold: u8,
+    /// Percentage the battery must recover above a threshold before leaving its block
+    pub battery_hysteresis: u8,
     pub lid_close_action: LidCloseAction,
     pub lid_open_action: LidOpenAction
 }
 
+impl std::fmt::Display for FullscreenInhibit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FullscreenInhibit::Off => write!(f, "false"),
+            FullscreenInhibit::Any => write!(f, "true"),
+            FullscreenInhibit::FocusedOutput => write!(f, "focused-output"),
+            FullscreenInhibit::Apps => write!(f, "apps"),
+        }
+    }
+}
+
 impl std::fmt::Display for LidCloseAction {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -1,14 +1,66 @@
+use chrono::NaiveTime;
 use eyre::{Result, eyre, WrapErr};
 use regex::Regex;
-use rune_cfg::{RuneConfig, Value};
-use std::path::PathBuf;
+use rune_cfg::{RuneConfig, RuneError, Value};
 
 use crate::{
-    config::model::*,
-    log::log_message,
-    core::utils::{detect_chassis, ChassisKind},
+    config::{
+        check::{ConfigDiagnostic, Severity},
+        config_search_paths,
+        layers::{ConfigLayer, LayeredConfig},
+        model::*,
+        ConfigSource,
+    },
+    log::{log_error_message, log_message},
+    core::utils::{detect_chassis, parse_duration, ChassisKind},
 };
 
+/// A timeout in seconds, written either as a number or a duration string ("5m", "1h30m")
+struct Seconds(u64);
+
+impl TryFrom<Value> for Seconds {
+    type Error = RuneError;
+
+    fn try_from(value: Value) -> std::result::Result<Self, Self::Error> {
+        match value {
+            Value::String(s) => parse_duration(&s)
+                .map(|d| Seconds(d.as_secs()))
+                .ok_or_else(|| RuneError::TypeError {
+                    message: format!("Invalid duration '{}'", s),
+                    line: 0,
+                    column: 0,
+                    hint: Some("Use seconds (300) or a duration like \"90s\", \"5m\" or \"1h30m\"".into()),
+                    code: Some(402),
+                }),
+            other => u64::try_from(other).map(Seconds),
+        }
+    }
+}
+
+/// `true`/`false`, or a string restricting which fullscreen windows count
+impl TryFrom<Value> for FullscreenInhibit {
+    type Error = RuneError;
+
+    fn try_from(value: Value) -> std::result::Result<Self, Self::Error> {
+        match value {
+            Value::Bool(true) => Ok(FullscreenInhibit::Any),
+            Value::Bool(false) => Ok(FullscreenInhibit::Off),
+            Value::String(s) => match s.as_str() {
+                "focused_output" | "focused-output" | "focused" => Ok(FullscreenInhibit::FocusedOutput),
+                "apps" | "inhibit_apps" | "inhibit-apps" => Ok(FullscreenInhibit::Apps),
+                _ => Err(RuneError::TypeError {
+                    message: format!("Invalid inhibit_on_fullscreen '{}'", s),
+                    line: 0,
+                    column: 0,
+                    hint: Some("Use true, false, \"focused-output\" or \"apps\"".into()),
+                    code: Some(402),
+                }),
+            },
+            other => bool::try_from(other).map(|on| if on { FullscreenInhibit::Any } else { FullscreenInhibit::Off }),
+        }
+    }
+}
+
 fn parse_app_pattern(s: &str) -> Result<AppInhibitPattern> {
     let regex_meta = ['.', '*', '+', '?', '(', ')', '[', ']', '{', '}', '|', '\\', '^', '$'];
     if s.chars().any(|c| regex_meta.contains(&c)) {
@@ -26,64 +78,98 @@ fn is_special_key(key: &str) -> bool {
             | "monitor_media" | "monitor-media"
             | "ignore_remote_media" | "ignore-remote-media"
             | "respect_wayland_inhibitors" | "respect-wayland-inhibitors"
+            | "respect_logind_inhibitors" | "respect-logind-inhibitors"
+            | "exit_with_compositor" | "exit-with-compositor"
             | "inhibit_apps" | "inhibit-apps"
+            | "inhibit_on_fullscreen" | "inhibit-on-fullscreen"
+            | "inhibit_rules" | "inhibit-rules"
+            | "inhibit_scopes" | "inhibit-scopes"
             | "debounce_seconds" | "debounce-seconds"
+            | "on_ac" | "on-ac"
+            | "on_battery" | "on-battery"
+            | "on_battery_low" | "on-battery-low"
+            | "on_battery_critical" | "on-battery-critical"
+            | "battery_hysteresis" | "battery-hysteresis"
+            | "profiles"
+            | "schedule"
     )
 }
 
-fn collect_actions(config: &RuneConfig, path: &str) -> Result<Vec<IdleActionBlock>> {
+fn collect_actions(
+    config: &LayeredConfig,
+    path: &str,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> Result<Vec<IdleActionBlock>> {
     let mut actions = Vec::new();
 
-    let keys = config
-        .get_keys(path)
-        .or_else(|_| config.get_keys(&path.replace('-', "_")))
-        .unwrap_or_default();
+    let keys = config.get_keys(path).unwrap_or_default();
 
     for key in keys {
         if is_special_key(&key) {
             continue;
         }
 
-        let command_path = format!("{}.{}.command", path, key);
-        let command = match config
-            .get::<String>(&command_path)
-            .or_else(|_| config.get::<String>(&command_path.replace('-', "_")))
-        {
-            Ok(c) => c,
-            Err(_) => continue,
-        };
-
-        let timeout_path = format!("{}.{}.timeout", path, key);
-        let timeout = match config
-            .get::<u64>(&timeout_path)
-            .or_else(|_| config.get::<u64>(&timeout_path.replace('-', "_")))
-        {
-            Ok(t) => t,
-            Err(_) => continue,
-        };
+        let block_path = format!("{}.{}", path, key);
+        let is_block = config.get_keys(&block_path).is_ok();
 
         let kind = match key.as_str() {
             "lock_screen" | "lock-screen" => IdleAction::LockScreen,
             "suspend" => IdleAction::Suspend,
+            "hibernate" => IdleAction::Hibernate,
+            "hybrid_sleep" | "hybrid-sleep" => IdleAction::HybridSleep,
+            "suspend_then_hibernate" | "suspend-then-hibernate" => IdleAction::SuspendThenHibernate,
             "dpms" => IdleAction::Dpms,
             "brightness" => IdleAction::Brightness,
+            "keyboard_backlight" | "keyboard-backlight" | "kbd_backlight" => IdleAction::KeyboardBacklight,
             _ => IdleAction::Custom,
         };
 
-        let resume_command = config
-            .get::<String>(&format!("{}.{}.resume_command", path, key))
-            .ok()
-            .or_else(|| config.get::<String>(&format!("{}.{}.resume-command", path, key)).ok());
+        let backlight = match kind {
+            IdleAction::Brightness if is_block => parse_backlight(config, &block_path, None, diagnostics),
+            // Keyboard backlights are switched off unless told otherwise
+            IdleAction::KeyboardBacklight if is_block => parse_backlight(config, &block_path, Some(0), diagnostics),
+            _ => None,
+        };
+
+        let command_path = format!("{}.command", block_path);
+        let command = match config.get::<String>(&command_path) {
+            Ok(c) => c,
+            // Sleep, dpms and leveled brightness actions work natively without a command
+            Err(RuneError::SyntaxError { code: Some(304), .. })
+                if is_block && (kind.has_native() || backlight.is_some()) => String::new(),
+            Err(e) => {
+                // Plain settings are linted separately; only report skipped blocks here
+                if is_block {
+                    diagnostics.push(skipped_action(config, &block_path, &key, "command", &e));
+                }
+                continue;
+            }
+        };
+
+        let timeout_path = format!("{}.timeout", block_path);
+        let timeout = match config.get::<Seconds>(&timeout_path) {
+            Ok(Seconds(t)) => t,
+            Err(e) => {
+                diagnostics.push(skipped_action(config, &block_path, &key, "timeout", &e));
+                continue;
+            }
+        };
+
+        let resume_command = config.get::<String>(&format!("{}.resume_command", block_path)).ok();
 
         let lock_command = if kind == IdleAction::LockScreen {
-            config
-                .get::<String>(&format!("{}.{}.lock_command", path, key))
-                .ok()
-                .or_else(|| config.get::<String>(&format!("{}.{}.lock-command", path, key)).ok())
+            config.get::<String>(&format!("{}.lock_command", block_path)).ok()
         } else {
             None
         };
 
+        if kind.light_subsystem().is_some() {
+            let known: Vec<&str> = KNOWN_ACTION_FIELDS.iter().chain(KNOWN_BACKLIGHT_FIELDS).copied().collect();
+            check_unknown_keys(config, &block_path, &known, diagnostics);
+        } else {
+            check_unknown_keys(config, &block_path, KNOWN_ACTION_FIELDS, diagnostics);
+        }
+
         actions.push(IdleActionBlock {
             name: key.clone(),
             timeout,
@@ -91,84 +177,723 @@ fn collect_actions(config: &RuneConfig, path: &str) -> Result<Vec<IdleActionBloc
             kind,
             resume_command,
             lock_command,
+            backlight,
             last_triggered: None,
+            skipped: false,
         });
     }
 
     Ok(actions)
 }
 
-// --- Helper to merge configs by loading the last one that exists ---
-fn load_merged_config() -> Result<RuneConfig> {
-    let mut config: Option<RuneConfig> = None;
-    let mut found_any = false;
-
-    // 1. Internal defaults (embedded from examples/)
-    // Note: This will be compiled into the binary
-    let internal_default = include_str!("../../examples/stasis.rune");
-    if let Ok(internal) = RuneConfig::from_str(internal_default) {
-        config = Some(internal);
-        found_any = true;
-    }
-
-    // 2. Shipped defaults (/usr/share/stasis/stasis.rune)
-    let share_path = PathBuf::from("/usr/share/stasis/stasis.rune");
-    if share_path.exists() {
-        if let Ok(shared) = RuneConfig::from_file(&share_path) {
-            config = Some(shared);
-            found_any = true;
+/// Native dimming settings of a light block; only active when a level is set or defaulted
+fn parse_backlight(
+    config: &LayeredConfig,
+    block_path: &str,
+    default_level: Option<u8>,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> Option<BacklightTarget> {
+    let level_path = format!("{}.level", block_path);
+    let fade_path = format!("{}.fade", block_path);
+    let device = config.get::<String>(&format!("{}.device", block_path)).ok();
+    let fade = get_setting::<Seconds>(config, &fade_path, diagnostics).map_or(0, |Seconds(s)| s);
+
+    let Some(level) = get_percentage(config, &level_path, diagnostics).or(default_level) else {
+        if device.is_some() || fade > 0 {
+            diagnostics.push(config.diagnostic(
+                Severity::Warning,
+                block_path,
+                "`device` and `fade` only apply together with `level`".to_string(),
+            ));
         }
+        return None;
+    };
+
+    Some(BacklightTarget { device, level, fade: std::time::Duration::from_secs(fade) })
+}
+
+/// Diagnostic for an action block dropped because `field` is missing or invalid
+fn skipped_action(config: &LayeredConfig, block_path: &str, key: &str, field: &str, err: &RuneError) -> ConfigDiagnostic {
+    match err {
+        RuneError::SyntaxError { code: Some(304), .. } => config.diagnostic(
+            Severity::Warning,
+            block_path,
+            format!("action '{}' skipped: missing `{}`", key, field),
+        ),
+        other => config.diagnostic(
+            Severity::Error,
+            &format!("{}.{}", block_path, field),
+            format!("action '{}' skipped: invalid `{}`: {}", key, field, other),
+        ),
     }
+}
 
-    // 3. System config (/etc/stasis/stasis.rune)
-    let sys_path = PathBuf::from("/etc/stasis/stasis.rune");
-    if sys_path.exists() {
-        if let Ok(system) = RuneConfig::from_file(&sys_path) {
-            config = Some(system);
-            found_any = true;
-        }
+// --- Helper to stack every config layer that exists ---
+fn load_merged_config(source: &ConfigSource, diagnostics: &mut Vec<ConfigDiagnostic>) -> Result<LayeredConfig> {
+    // An explicit file is loaded as-is, without falling back to other layers
+    if let ConfigSource::Explicit(path) = source {
+        let config = RuneConfig::from_file(path)
+            .wrap_err_with(|| format!("failed to load config file {}", path.display()))?;
+        return Ok(LayeredConfig::new(vec![ConfigLayer { path: Some(path.clone()), config }]));
     }
 
-    // 4. User config (~/.config/stasis/stasis.rune) - highest priority
-    if let Some(mut user_path) = dirs::home_dir() {
-        user_path.push(".config/stasis/stasis.rune");
-        if user_path.exists() {
-            if let Ok(user) = RuneConfig::from_file(&user_path) {
-                config = Some(user);
-                found_any = true;
+    // Shipped defaults, system config and user config - later layers override earlier ones
+    let mut layers = Vec::new();
+    for path in config_search_paths(source) {
+        if path.exists() {
+            match RuneConfig::from_file(&path) {
+                Ok(config) => layers.push(ConfigLayer { path: Some(path), config }),
+                Err(e) => diagnostics.push(ConfigDiagnostic {
+                    severity: Severity::Error,
+                    key: "stasis".to_string(),
+                    message: format!("layer skipped, failed to parse: {}", e),
+                    file: Some(path),
+                    line: None,
+                }),
             }
         }
     }
 
-    if !found_any {
+    // Internal defaults (embedded from examples/) are only a fallback, so their
+    // example actions never leak into a user's own config
+    if layers.is_empty() {
+        let internal_default = include_str!("../../examples/stasis.rune");
+        if let Ok(config) = RuneConfig::from_str(internal_default) {
+            layers.push(ConfigLayer { path: None, config });
+        }
+    }
+
+    if layers.is_empty() {
         return Err(eyre!("no valid configuration found in any location"));
     }
 
-    config.ok_or_else(|| eyre!("failed to load any configuration"))
+    Ok(LayeredConfig::new(layers))
+}
+
+/// Settings understood under `stasis:` (action blocks aside)
+const KNOWN_SETTINGS: &[&str] = &[
+    "pre_suspend_command",
+    "monitor_media",
+    "ignore_remote_media",
+    "media_blacklist",
+    "respect_wayland_inhibitors",
+    "respect_logind_inhibitors",
+    "exit_with_compositor",
+    "lid_close_action",
+    "lid_open_action",
+    "debounce_seconds",
+    "inhibit_apps",
+    "inhibit_on_fullscreen",
+    "inhibit_rules",
+    "inhibit_scopes",
+    "battery_hysteresis",
+];
+
+/// Default `threshold` of the battery level blocks, in percent
+const DEFAULT_BATTERY_LOW: u8 = 20;
+const DEFAULT_BATTERY_CRITICAL: u8 = 5;
+const DEFAULT_BATTERY_HYSTERESIS: u8 = 3;
+
+/// Settings understood inside a profile (action blocks aside)
+const KNOWN_PROFILE_SETTINGS: &[&str] = &["inhibit", "monitor_media", "inhibit_apps"];
+
+/// Fields understood inside a schedule rule
+const KNOWN_SCHEDULE_FIELDS: &[&str] = &["days", "from", "to", "inhibit", "profile", "block"];
+
+/// Fields understood inside an inhibit rule
+const KNOWN_RULE_FIELDS: &[&str] = &["app_id", "title", "cmdline", "workspace", "output", "blocks"];
+
+/// Inhibitor sources whose scope can be set in `inhibit_scopes`
+const KNOWN_SCOPE_SOURCES: &[&str] = &["media", "apps", "fullscreen", "logind", "screensaver"];
+
+/// Fields understood inside an action block
+const KNOWN_ACTION_FIELDS: &[&str] = &["timeout", "command", "resume_command", "lock_command"];
+const KNOWN_BACKLIGHT_FIELDS: &[&str] = &["device", "level", "fade"];
+
+/// Read an optional setting, reporting values that are present but invalid
+fn get_setting<T>(config: &LayeredConfig, path: &str, diagnostics: &mut Vec<ConfigDiagnostic>) -> Option<T>
+where
+    T: TryFrom<Value, Error = RuneError>,
+{
+    match config.get::<T>(path) {
+        Ok(value) => Some(value),
+        Err(RuneError::SyntaxError { code: Some(304), .. }) => None,
+        Err(e) => {
+            diagnostics.push(config.diagnostic(Severity::Error, path, format!("invalid value, using default: {}", e)));
+            None
+        }
+    }
+}
+
+/// Read an optional percentage (0-100)
+fn get_percentage(config: &LayeredConfig, path: &str, diagnostics: &mut Vec<ConfigDiagnostic>) -> Option<u8> {
+    get_setting::<u8>(config, path, diagnostics).filter(|p| {
+        if *p > 100 {
+            diagnostics.push(config.diagnostic(
+                Severity::Error,
+                path,
+                format!("{}% is not a valid percentage, using default", p),
+            ));
+        }
+        *p <= 100
+    })
+}
+
+/// Edit distance between two keys, used to suggest the intended spelling
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut cur = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            cur.push((prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1));
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+fn unknown_key_message(key: &str, known: &[&str]) -> String {
+    let normalized = key.replace('-', "_");
+    let suggestion = known
+        .iter()
+        .map(|k| (edit_distance(&normalized, k), k))
+        .filter(|(d, k)| *d <= (k.len() / 3).max(2))
+        .min_by_key(|(d, _)| *d);
+
+    match suggestion {
+        Some((_, k)) => format!("unknown key '{}' is ignored (did you mean '{}'?)", key, k),
+        None => format!("unknown key '{}' is ignored", key),
+    }
+}
+
+/// Report keys under `path` that are neither known settings nor action blocks
+fn check_unknown_keys(
+    config: &LayeredConfig,
+    path: &str,
+    known: &[&str],
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) {
+    for key in config.get_keys(path).unwrap_or_default() {
+        let key_path = format!("{}.{}", path, key);
+        let is_block = config.get_keys(&key_path).is_ok();
+        if is_block || known.contains(&key.replace('-', "_").as_str()) {
+            continue;
+        }
+        diagnostics.push(config.diagnostic(Severity::Warning, &key_path, unknown_key_message(&key, known)));
+    }
+}
+
+/// Warn about stages ordered after a sleep action: timeouts count from the
+/// previous action, so they could only fire once the machine is already asleep
+fn check_action_order(config: &LayeredConfig, path: &str, actions: &[IdleActionBlock], diagnostics: &mut Vec<ConfigDiagnostic>) {
+    let Some(suspend_index) = actions.iter().position(|a| a.kind.is_sleep()) else {
+        return;
+    };
+
+    for action in &actions[suspend_index + 1..] {
+        if matches!(action.kind, IdleAction::Brightness | IdleAction::Dpms | IdleAction::LockScreen) {
+            diagnostics.push(config.diagnostic(
+                Severity::Warning,
+                &format!("{}.{}", path, action.name),
+                format!(
+                    "'{}' is ordered after '{}' and will never run before the system suspends",
+                    action.name, actions[suspend_index].name
+                ),
+            ));
+        }
+    }
+}
+
+/// Collect the actions of one block and lint it
+fn collect_block(
+    config: &LayeredConfig,
+    path: &str,
+    prefix: Option<&str>,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> Result<Vec<IdleActionBlock>> {
+    let actions = collect_actions(config, path, diagnostics)?;
+    check_action_order(config, path, &actions, diagnostics);
+
+    Ok(actions
+        .into_iter()
+        .map(|mut a| {
+            if let Some(prefix) = prefix {
+                a.name = format!("{}.{}", prefix, a.name);
+            }
+            a
+        })
+        .collect())
+}
+
+/// Parse an inhibit_apps list, reporting patterns that fail to compile
+fn parse_inhibit_apps(
+    config: &LayeredConfig,
+    path: &str,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> Option<Vec<AppInhibitPattern>> {
+    match config.get_value(path).ok()? {
+        Value::Array(arr) => Some(
+            arr.iter()
+                .filter_map(|v| {
+                    let parsed = match v {
+                        Value::String(s) => parse_app_pattern(s),
+                        Value::Regex(s) => Regex::new(s)
+                            .map(AppInhibitPattern::Regex)
+                            .wrap_err("invalid regex in inhibit_apps"),
+                        _ => return None,
+                    };
+                    match parsed {
+                        Ok(pattern) => Some(pattern),
+                        Err(e) => {
+                            diagnostics.push(config.diagnostic(
+                                Severity::Error,
+                                path,
+                                format!("pattern skipped: {:#}", e),
+                            ));
+                            None
+                        }
+                    }
+                })
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Read one matcher of an inhibit rule, a string (regex if it has metacharacters) or r"regex"
+fn get_pattern(config: &LayeredConfig, path: &str, diagnostics: &mut Vec<ConfigDiagnostic>) -> Option<AppInhibitPattern> {
+    let parsed = match config.get_value(path).ok()? {
+        Value::String(s) => parse_app_pattern(&s),
+        Value::Regex(s) => Regex::new(&s)
+            .map(AppInhibitPattern::Regex)
+            .wrap_err("invalid regex"),
+        _ => Err(eyre!("expected a string or regex")),
+    };
+    match parsed {
+        Ok(pattern) => Some(pattern),
+        Err(e) => {
+            diagnostics.push(config.diagnostic(Severity::Error, path, format!("matcher ignored: {:#}", e)));
+            None
+        }
+    }
+}
+
+/// Read a `blocks` setting: action kinds or action names, everything when unset
+fn parse_scope(
+    config: &LayeredConfig,
+    path: &str,
+    actions: &[IdleActionBlock],
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> InhibitScope {
+    let names: Vec<String> = match config.get_value(path).ok() {
+        None => return InhibitScope::All,
+        Some(Value::String(s)) => vec![s],
+        Some(Value::Array(arr)) => arr
+            .iter()
+            .filter_map(|v| match v {
+                Value::String(s) => Some(s.clone()),
+                _ => None,
+            })
+            .collect(),
+        Some(_) => {
+            diagnostics.push(config.diagnostic(
+                Severity::Error,
+                path,
+                "expected an action or a list of actions, blocking everything".to_string(),
+            ));
+            return InhibitScope::All;
+        }
+    };
+
+    let names: Vec<String> = names.iter().map(|n| n.trim().to_lowercase().replace('-', "_")).collect();
+    if names.iter().any(|n| n == "all") {
+        return InhibitScope::All;
+    }
+
+    for name in &names {
+        let single = InhibitScope::Only(vec![name.clone()]);
+        if !actions.iter().any(|a| single.blocks(a)) {
+            diagnostics.push(config.diagnostic(
+                Severity::Warning,
+                path,
+                format!("'{}' matches no configured action", name),
+            ));
+        }
+    }
+    InhibitScope::Only(names)
+}
+
+/// Collect every rule under `stasis.inhibit_rules`
+fn collect_inhibit_rules(
+    config: &LayeredConfig,
+    actions: &[IdleActionBlock],
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> Vec<AppInhibitRule> {
+    let mut rules = Vec::new();
+
+    for name in config.get_keys("stasis.inhibit_rules").unwrap_or_default() {
+        let path = format!("stasis.inhibit_rules.{}", name);
+        if config.get_keys(&path).is_err() {
+            diagnostics.push(config.diagnostic(
+                Severity::Warning,
+                &path,
+                format!("inhibit rule '{}' skipped: expected a block", name),
+            ));
+            continue;
+        }
+
+        check_unknown_keys(config, &path, KNOWN_RULE_FIELDS, diagnostics);
+
+        let rule = AppInhibitRule {
+            app_id: get_pattern(config, &format!("{}.app_id", path), diagnostics),
+            title: get_pattern(config, &format!("{}.title", path), diagnostics),
+            cmdline: get_pattern(config, &format!("{}.cmdline", path), diagnostics),
+            workspace: get_pattern(config, &format!("{}.workspace", path), diagnostics),
+            output: get_pattern(config, &format!("{}.output", path), diagnostics),
+            scope: parse_scope(config, &format!("{}.blocks", path), actions, diagnostics),
+            name,
+        };
+
+        if !rule.needs_window() && rule.cmdline.is_none() {
+            diagnostics.push(config.diagnostic(
+                Severity::Warning,
+                &path,
+                format!(
+                    "inhibit rule '{}' skipped: set at least one of app_id, title, cmdline, workspace or output",
+                    rule.name
+                ),
+            ));
+            continue;
+        }
+
+        rules.push(rule);
+    }
+
+    rules
+}
+
+/// Read `stasis.inhibit_scopes`; sources left out block every action
+fn parse_inhibit_scopes(
+    config: &LayeredConfig,
+    actions: &[IdleActionBlock],
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> InhibitScopes {
+    let path = "stasis.inhibit_scopes";
+    if config.get_keys(path).is_err() {
+        return InhibitScopes::default();
+    }
+
+    check_unknown_keys(config, path, KNOWN_SCOPE_SOURCES, diagnostics);
+    let scope = |source: &str, diagnostics: &mut Vec<ConfigDiagnostic>| {
+        parse_scope(config, &format!("{}.{}", path, source), actions, diagnostics)
+    };
+
+    InhibitScopes {
+        media: scope("media", diagnostics),
+        apps: scope("apps", diagnostics),
+        fullscreen: scope("fullscreen", diagnostics),
+        logind: scope("logind", diagnostics),
+        screensaver: scope("screensaver", diagnostics),
+    }
+}
+
+/// Collect every profile under `stasis.profiles`
+fn collect_profiles(config: &LayeredConfig, diagnostics: &mut Vec<ConfigDiagnostic>) -> Result<Vec<Profile>> {
+    let mut profiles = Vec::new();
+
+    for name in config.get_keys("stasis.profiles").unwrap_or_default() {
+        let path = format!("stasis.profiles.{}", name);
+        if config.get_keys(&path).is_err() {
+            diagnostics.push(config.diagnostic(
+                Severity::Warning,
+                &path,
+                format!("profile '{}' skipped: expected a block", name),
+            ));
+            continue;
+        }
+
+        check_unknown_keys(config, &path, KNOWN_PROFILE_SETTINGS, diagnostics);
+
+        let actions = collect_block(config, &path, None, diagnostics)?;
+        let inhibit = get_setting::<bool>(config, &format!("{}.inhibit", path), diagnostics).unwrap_or(false);
+        let monitor_media = get_setting::<bool>(config, &format!("{}.monitor_media", path), diagnostics);
+        let inhibit_apps = parse_inhibit_apps(config, &format!("{}.inhibit_apps", path), diagnostics);
+
+        profiles.push(Profile {
+            name,
+            actions,
+            inhibit,
+            monitor_media,
+            inhibit_apps,
+        });
+    }
+
+    Ok(profiles)
+}
+
+/// Parse a day spec such as "mon-fri", "weekends", "daily" or "mon,wed,fri".
+/// Days are given by their full or three-letter English name.
+fn parse_days(spec: &str) -> Result<[bool; 7], String> {
+    const NAMES: [(&str, &str); 7] = [
+        ("mon", "monday"),
+        ("tue", "tuesday"),
+        ("wed", "wednesday"),
+        ("thu", "thursday"),
+        ("fri", "friday"),
+        ("sat", "saturday"),
+        ("sun", "sunday"),
+    ];
+    let day_index = |s: &str| {
+        let s = s.trim().to_lowercase();
+        NAMES
+            .iter()
+            .position(|(short, long)| s == *short || s == *long)
+            .ok_or_else(|| format!("unknown day '{}'", s))
+    };
+
+    let mut days = [false; 7];
+    for part in spec.split(',') {
+        match part.trim().to_lowercase().as_str() {
+            "daily" | "all" | "everyday" => days = [true; 7],
+            "weekdays" => days[..5].fill(true),
+            "weekends" => days[5..].fill(true),
+            range => match range.split_once('-') {
+                Some((from, to)) => {
+                    let (from, to) = (day_index(from)?, day_index(to)?);
+                    // Ranges may wrap around the week, e.g. "fri-mon"
+                    let mut i = from;
+                    loop {
+                        days[i] = true;
+                        if i == to {
+                            break;
+                        }
+                        i = (i + 1) % 7;
+                    }
+                }
+                None => days[day_index(range)?] = true,
+            },
+        }
+    }
+
+    if days.iter().any(|d| *d) {
+        Ok(days)
+    } else {
+        Err("no days given".to_string())
+    }
+}
+
+/// Collect every rule under `stasis.schedule`
+fn collect_schedule(
+    config: &LayeredConfig,
+    profiles: &[Profile],
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> Vec<ScheduleRule> {
+    let mut rules = Vec::new();
+
+    for name in config.get_keys("stasis.schedule").unwrap_or_default() {
+        let path = format!("stasis.schedule.{}", name);
+        if config.get_keys(&path).is_err() {
+            diagnostics.push(config.diagnostic(
+                Severity::Warning,
+                &path,
+                format!("schedule rule '{}' skipped: expected a block", name),
+            ));
+            continue;
+        }
+
+        check_unknown_keys(config, &path, KNOWN_SCHEDULE_FIELDS, diagnostics);
+
+        let days = match config.get_value(&format!("{}.days", path)).ok() {
+            None => Ok([true; 7]),
+            Some(Value::String(spec)) => parse_days(&spec),
+            Some(Value::Array(arr)) => {
+                let spec = arr
+                    .iter()
+                    .filter_map(|v| match v {
+                        Value::String(s) => Some(s.as_str()),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",");
+                parse_days(&spec)
+            }
+            Some(_) => Err("expected a string or an array of strings".to_string()),
+        };
+        if let Err(e) = &days {
+            diagnostics.push(config.diagnostic(
+                Severity::Error,
+                &format!("{}.days", path),
+                format!("schedule rule '{}' skipped: invalid `days`: {} (expected e.g. \"mon-fri\", \"weekends\" or \"daily\")", name, e),
+            ));
+        }
+
+        let mut times = Vec::new();
+        for field in ["from", "to"] {
+            let field_path = format!("{}.{}", path, field);
+            let parsed = get_setting::<String>(config, &field_path, diagnostics)
+                .and_then(|s| NaiveTime::parse_from_str(s.trim(), "%H:%M").ok());
+            match parsed {
+                Some(time) => times.push(time),
+                None => diagnostics.push(config.diagnostic(
+                    Severity::Error,
+                    &field_path,
+                    format!("schedule rule '{}' skipped: missing or invalid `{}` (expected \"HH:MM\")", name, field),
+                )),
+            }
+        }
+        let inhibit = get_setting::<bool>(config, &format!("{}.inhibit", path), diagnostics).unwrap_or(false);
+
+        let profile = get_setting::<String>(config, &format!("{}.profile", path), diagnostics).filter(|p| {
+            let exists = profiles.iter().any(|profile| &profile.name == p);
+            if !exists {
+                diagnostics.push(config.diagnostic(
+                    Severity::Error,
+                    &format!("{}.profile", path),
+                    format!("unknown profile '{}' is ignored", p),
+                ));
+            }
+            exists
+        });
+
+        let block = get_setting::<String>(config, &format!("{}.block", path), diagnostics).filter(|b| {
+            let valid = matches!(b.as_str(), "default" | "ac" | "battery");
+            if !valid {
+                diagnostics.push(config.diagnostic(
+                    Severity::Error,
+                    &format!("{}.block", path),
+                    format!("unknown block '{}' is ignored (expected default, ac or battery)", b),
+                ));
+            }
+            valid
+        });
+
+        if !inhibit && profile.is_none() && block.is_none() {
+            diagnostics.push(config.diagnostic(
+                Severity::Warning,
+                &path,
+                format!("schedule rule '{}' has no effect (set `inhibit`, `profile` or `block`)", name),
+            ));
+        }
+
+        let (Ok(days), &[start, end]) = (days, &times[..]) else { continue };
+
+        rules.push(ScheduleRule {
+            name,
+            days,
+            start,
+            end,
+            inhibit,
+            profile,
+            block,
+        });
+    }
+
+    rules
 }
 
 // --- main loader ---
-pub fn load_config() -> Result<StasisConfig> {
-    let config = load_merged_config().wrap_err("failed to load layered configuration")?;
+pub fn load_config(source: &ConfigSource) -> Result<StasisConfig> {
+    let mut diagnostics = Vec::new();
+    let result = parse_config(source, detect_chassis(), &mut diagnostics);
 
-    let pre_suspend_command = config
-        .get::<String>("stasis.pre_suspend_command")
-        .or_else(|_| config.get::<String>("stasis.pre-suspend-command"))
-        .ok();
+    for diagnostic in &diagnostics {
+        match diagnostic.severity {
+            Severity::Error => log_error_message(&diagnostic.to_string()),
+            Severity::Warning => log_message(&diagnostic.to_string()),
+        }
+    }
 
-    let monitor_media = config
-        .get::<bool>("stasis.monitor_media")
-        .or_else(|_| config.get::<bool>("stasis.monitor-media"))
-        .unwrap_or(true);
+    let cfg = result?;
 
-    let ignore_remote_media = config
-        .get::<bool>("stasis.ignore_remote_media")
-        .or_else(|_| config.get::<bool>("stasis.ignore-remote-media"))
-        .unwrap_or(true);
+    log_message("Parsed Config:");
+    log_message(&format!(
+        "  config_layers = [{}]",
+        cfg.config_layers.iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", ")
+    ));
+    log_message(&format!("  pre_suspend_command = {:?}", cfg.pre_suspend_command));
+    log_message(&format!("  monitor_media = {:?}", cfg.monitor_media));
+    log_message(&format!("  ignore_remote_media = {:?}", cfg.ignore_remote_media));
+    log_message(&format!(
+        "  media_blacklist = [{}]",
+        cfg.media_blacklist.join(", ")
+    ));
+    log_message(&format!("  respect_wayland_inhibitors = {:?}", cfg.respect_wayland_inhibitors));
+    log_message(&format!("  respect_logind_inhibitors = {:?}", cfg.respect_logind_inhibitors));
+    log_message(&format!("  exit_with_compositor = {:?}", cfg.exit_with_compositor));
+    log_message(&format!("  debounce_seconds = {:?}", cfg.debounce_seconds));
+    log_message(&format!(
+        "  battery thresholds = low {}%, critical {}%, hysteresis {}%",
+        cfg.battery_low_threshold, cfg.battery_critical_threshold, cfg.battery_hysteresis
+    ));
+    log_message(&format!("  lid_close_action = {:?}", cfg.lid_close_action));
+    log_message(&format!("  lid_open_action = {:?}", cfg.lid_open_action));
+    log_message(&format!(
+        "  inhibit_apps = [{}]",
+        cfg.inhibit_apps.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
+    ));
+    log_message(&format!("  inhibit_on_fullscreen = {}", cfg.inhibit_on_fullscreen));
+    for rule in &cfg.inhibit_rules {
+        log_message(&format!("  inhibit rule {}: {} (blocks {})", rule.name, rule.matchers(), rule.scope));
+    }
+    for (source, scope) in cfg.inhibit_scopes.entries() {
+        if *scope != InhibitScope::All {
+            log_message(&format!("  {} inhibitor blocks {}", source, scope));
+        }
+    }
+    log_message("  actions:");
+    for action in &cfg.actions {
+        let mut details = format!(
+            "    {}: timeout={}s, command={}",
+            action.name, action.timeout, action.command_label()
+        );
+        if let Some(resume_cmd) = &action.resume_command {
+            details.push_str(&format!(", resume_command=\"{}\"", resume_cmd));
+        }
+        log_message(&details);
+    }
+    for profile in &cfg.profiles {
+        log_message(&format!(
+            "  profile {}: actions=[{}], inhibit={}, monitor_media={:?}",
+            profile.name,
+            profile.actions.iter().map(|a| a.name.as_str()).collect::<Vec<_>>().join(", "),
+            profile.inhibit,
+            profile.monitor_media
+        ));
+    }
+    for rule in &cfg.schedule {
+        log_message(&format!(
+            "  schedule {}: {}, inhibit={}, profile={:?}, block={:?}",
+            rule.name,
+            rule.window(),
+            rule.inhibit,
+            rule.profile,
+            rule.block
+        ));
+    }
+
+    Ok(cfg)
+}
+
+/// Parse the config for the given chassis, recording every problem in `diagnostics`
+pub fn parse_config(
+    source: &ConfigSource,
+    chassis: ChassisKind,
+    diagnostics: &mut Vec<ConfigDiagnostic>,
+) -> Result<StasisConfig> {
+    let config = load_merged_config(source, diagnostics).wrap_err("failed to load layered configuration")?;
+    let config_path = config.top_path();
+    let config_layers = config.paths();
+
+    let pre_suspend_command = get_setting::<String>(&config, "stasis.pre_suspend_command", diagnostics);
+
+    let monitor_media = get_setting::<bool>(&config, "stasis.monitor_media", diagnostics).unwrap_or(true);
+
+    let ignore_remote_media = get_setting::<bool>(&config, "stasis.ignore_remote_media", diagnostics).unwrap_or(true);
 
     let media_blacklist: Vec<String> = config
         .get_value("stasis.media_blacklist")
-        .or_else(|_| config.get_value("stasis.media-blacklist"))
         .ok()
         .and_then(|v| match v {
             Value::Array(arr) => Some(
@@ -183,15 +908,14 @@ pub fn load_config() -> Result<StasisConfig> {
         })
         .unwrap_or_default();
 
-    let respect_wayland_inhibitors = config
-        .get::<bool>("stasis.respect_wayland_inhibitors")
-        .or_else(|_| config.get::<bool>("stasis.respect-wayland-inhibitors"))
-        .unwrap_or(true);
+    let respect_wayland_inhibitors =
+        get_setting::<bool>(&config, "stasis.respect_wayland_inhibitors", diagnostics).unwrap_or(true);
+    let respect_logind_inhibitors =
+        get_setting::<bool>(&config, "stasis.respect_logind_inhibitors", diagnostics).unwrap_or(true);
+    let exit_with_compositor =
+        get_setting::<bool>(&config, "stasis.exit_with_compositor", diagnostics).unwrap_or(false);
 
-    let lid_close_action = config
-        .get::<String>("stasis.lid_close_action")
-        .or_else(|_| config.get::<String>("stasis.lid-close-action"))
-        .ok()
+    let lid_close_action = get_setting::<String>(&config, "stasis.lid_close_action", diagnostics)
         .map(|s| match s.as_str() {
             "ignore" => LidCloseAction::Ignore,
             "lock_screen" | "lock-screen" => LidCloseAction::LockScreen,
@@ -200,19 +924,17 @@ pub fn load_config() -> Result<StasisConfig> {
                 LidCloseAction::Custom(other.trim_start_matches("custom:").trim().to_string())
             }
             _ => {
-                log_message(&format!(
-                    "Unknown lid_close_action '{}', defaulting to ignore",
-                    s
+                diagnostics.push(config.diagnostic(
+                    Severity::Warning,
+                    "stasis.lid_close_action",
+                    format!("unknown lid_close_action '{}', defaulting to ignore (expected lock-screen, suspend, custom:<command> or ignore)", s),
                 ));
                 LidCloseAction::Ignore
             }
         })
         .unwrap_or(LidCloseAction::Ignore);
 
-    let lid_open_action = config
-        .get::<String>("stasis.lid_open_action")
-        .or_else(|_| config.get::<String>("stasis.lid-open-action"))
-        .ok()
+    let lid_open_action = get_setting::<String>(&config, "stasis.lid_open_action", diagnostics)
         .map(|s| match s.as_str() {
             "ignore" => LidOpenAction::Ignore,
             "wake" => LidOpenAction::Wake,
@@ -220,105 +942,103 @@ pub fn load_config() -> Result<StasisConfig> {
                 LidOpenAction::Custom(other.trim_start_matches("custom:").trim().to_string())
             }
             _ => {
-                log_message(&format!(
-                    "Unknown lid_open_action '{}', defaulting to ignore",
-                    s
+                diagnostics.push(config.diagnostic(
+                    Severity::Warning,
+                    "stasis.lid_open_action",
+                    format!("unknown lid_open_action '{}', defaulting to ignore (expected wake, custom:<command> or ignore)", s),
                 ));
                 LidOpenAction::Ignore
             }
         })
         .unwrap_or(LidOpenAction::Ignore);
 
-    let debounce_seconds = config
-        .get::<u8>("stasis.debounce_seconds")
-        .or_else(|_| config.get::<u8>("stasis.debounce-seconds"))
+    let debounce_seconds = get_setting::<Seconds>(&config, "stasis.debounce_seconds", diagnostics)
+        .and_then(|Seconds(secs)| match u8::try_from(secs) {
+            Ok(secs) => Some(secs),
+            Err(_) => {
+                diagnostics.push(config.diagnostic(
+                    Severity::Error,
+                    "stasis.debounce_seconds",
+                    format!("debounce of {}s is too long (maximum 255s), using default", secs),
+                ));
+                None
+            }
+        })
         .unwrap_or(0u8);
 
-    let inhibit_apps: Vec<AppInhibitPattern> = config
-        .get_value("stasis.inhibit_apps")
-        .or_else(|_| config.get_value("stasis.inhibit-apps"))
-        .ok()
-        .and_then(|v| match v {
-            Value::Array(arr) => Some(
-                arr.iter()
-                    .filter_map(|v| match v {
-                        Value::String(s) => parse_app_pattern(s).ok(),
-                        Value::Regex(s) => Regex::new(s).ok().map(AppInhibitPattern::Regex),
-                        _ => None,
-                    })
-                    .collect(),
+    let inhibit_apps = parse_inhibit_apps(&config, "stasis.inhibit_apps", diagnostics).unwrap_or_default();
+    let inhibit_on_fullscreen =
+        get_setting::<FullscreenInhibit>(&config, "stasis.inhibit_on_fullscreen", diagnostics).unwrap_or_default();
+
+    check_unknown_keys(&config, "stasis", KNOWN_SETTINGS, diagnostics);
+
+    let battery_low_threshold = get_percentage(&config, "stasis.on_battery_low.threshold", diagnostics)
+        .unwrap_or(DEFAULT_BATTERY_LOW);
+    let battery_critical_threshold = get_percentage(&config, "stasis.on_battery_critical.threshold", diagnostics)
+        .unwrap_or(DEFAULT_BATTERY_CRITICAL);
+    let battery_hysteresis = get_percentage(&config, "stasis.battery_hysteresis", diagnostics)
+        .unwrap_or(DEFAULT_BATTERY_HYSTERESIS);
+
+    if battery_critical_threshold >= battery_low_threshold && matches!(chassis, ChassisKind::Laptop) {
+        diagnostics.push(config.diagnostic(
+            Severity::Warning,
+            "stasis.on_battery_critical.threshold",
+            format!(
+                "critical threshold ({}%) is not below the low threshold ({}%), on_battery_low will never apply",
+                battery_critical_threshold, battery_low_threshold
             ),
-            _ => None,
-        })
-        .unwrap_or_default();
+        ));
+    }
 
-    let chassis = detect_chassis();
     let actions = match chassis {
         ChassisKind::Laptop => {
             let mut all = Vec::new();
-            // Collect with "ac." prefix
-            let ac_actions = collect_actions(&config, "stasis.on_ac")?
-                .into_iter()
-                .map(|mut a| {
-                    a.name = format!("ac.{}", a.name);
-                    a
-                });
-            all.extend(ac_actions);
-            
-            // Collect with "battery." prefix
-            let battery_actions = collect_actions(&config, "stasis.on_battery")?
-                .into_iter()
-                .map(|mut a| {
-                    a.name = format!("battery.{}", a.name);
-                    a
-                });
-            all.extend(battery_actions);
-            
+            for (path, prefix, known) in [
+                ("stasis.on_ac", "ac", &[][..]),
+                ("stasis.on_battery", "battery", &[][..]),
+                ("stasis.on_battery_low", "battery_low", &["threshold"][..]),
+                ("stasis.on_battery_critical", "battery_critical", &["threshold"][..]),
+            ] {
+                check_unknown_keys(&config, path, known, diagnostics);
+                all.extend(collect_block(&config, path, Some(prefix), diagnostics)?);
+            }
             all
-        }   
-        ChassisKind::Desktop => collect_actions(&config, "stasis")?,
+        }
+        ChassisKind::Desktop => collect_block(&config, "stasis", None, diagnostics)?,
     };
 
     if actions.is_empty() {
         return Err(eyre!("no valid idle actions found in config"));
     }
 
-    log_message("Parsed Config:");
-    log_message(&format!("  pre_suspend_command = {:?}", pre_suspend_command));
-    log_message(&format!("  monitor_media = {:?}", monitor_media));
-    log_message(&format!("  ignore_remote_media = {:?}", ignore_remote_media));
-    log_message(&format!(
-        "  media_blacklist = [{}]",
-        media_blacklist.join(", ")
-    ));
-    log_message(&format!("  respect_wayland_inhibitors = {:?}", respect_wayland_inhibitors));
-    log_message(&format!("  debounce_seconds = {:?}", debounce_seconds));
-    log_message(&format!("  lid_close_action = {:?}", lid_close_action));
-    log_message(&format!("  lid_open_action = {:?}", lid_open_action));
-    log_message(&format!(
-        "  inhibit_apps = [{}]",
-        inhibit_apps.iter().map(|p| p.to_string()).collect::<Vec<_>>().join(", ")
-    ));
-    log_message("  actions:");
-    for action in &actions {
-        let mut details = format!(
-            "    {}: timeout={}s, command=\"{}\"",
-            action.name, action.timeout, action.command
-        );
-        if let Some(resume_cmd) = &action.resume_command {
-            details.push_str(&format!(", resume_command=\"{}\"", resume_cmd));
-        }
-        log_message(&details);
-    }
+    let profiles = collect_profiles(&config, diagnostics)?;
+    let inhibit_rules = collect_inhibit_rules(&config, &actions, diagnostics);
+    let inhibit_scopes = parse_inhibit_scopes(&config, &actions, diagnostics);
+    let schedule = collect_schedule(&config, &profiles, diagnostics);
+
+    let value_sources = config.into_sources();
 
     Ok(StasisConfig {
         actions,
+        profiles,
+        schedule,
+        config_path,
+        config_layers,
+        value_sources,
         pre_suspend_command,
         monitor_media,
         media_blacklist, 
         ignore_remote_media,
         respect_wayland_inhibitors,
+        respect_logind_inhibitors,
+        exit_with_compositor,
+        battery_low_threshold,
+        battery_critical_threshold,
+        battery_hysteresis,
         inhibit_apps,
+        inhibit_on_fullscreen,
+        inhibit_rules,
+        inhibit_scopes,
         debounce_seconds,
         lid_close_action,
         lid_open_action,
@@ -1,8 +1,8 @@
 use std::sync::Arc;
 use tokio::sync::Mutex;
 
-use crate::{config::model::{IdleAction, LidCloseAction, LidOpenAction}, core::manager::{helpers::{run_action, wake_idle_tasks}, Manager}};
-use crate::log::log_message;
+use crate::{config::model::{IdleAction, LidCloseAction, LidOpenAction}, core::manager::{helpers::{run_action, spawn_logind_sleep, wake_idle_tasks}, Manager}};
+use crate::log::{log_error_message, log_message};
 
 pub enum Event {
     InputActivity,
@@ -10,6 +10,7 @@ pub enum Event {
     MediaPlaybackEnded,
     ACConnected,
     ACDisconnected,
+    BatteryCapacity(u8),
     LockScreenDetected,
     Suspend,
     Wake,
@@ -41,6 +42,19 @@ pub async fn handle_event(manager: &Arc<Mutex<Manager>>, event: Event) {
             mgr.trigger_instant_actions().await;
             wake_idle_tasks(&mgr.state);
         }
+        Event::BatteryCapacity(capacity) => {
+            let mut mgr = manager.lock().await;
+            let previous_block = mgr.state.current_block.clone();
+            mgr.state.set_battery_capacity(capacity);
+
+            if mgr.state.current_block != previous_block {
+                mgr.state.action_index = 0;
+                mgr.reset_instant_actions();
+                mgr.trigger_instant_actions().await;
+                wake_idle_tasks(&mgr.state);
+            }
+        }
+
         Event::InputActivity => {
             let mut mgr = manager.lock().await;
             mgr.reset().await;
@@ -102,6 +116,9 @@ pub async fn handle_event(manager: &Arc<Mutex<Manager>>, event: Event) {
                     LidCloseAction::Suspend => {
                         if let Some(suspend_action) = suspend_action_opt {
                             run_action(&mut mgr, &suspend_action).await;
+                        } else if let Err(e) = spawn_logind_sleep(&mgr.state, "Suspend") {
+                            // No suspend action configured, suspend natively
+                            log_error_message(&format!("Lid-close suspend failed: {}", e));
                         }
                     }
                     LidCloseAction::LockScreen => {
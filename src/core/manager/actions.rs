@@ -8,6 +8,8 @@ use crate::log::log_message;
 #[derive(Debug, Clone)]
 pub enum ActionRequest {
     RunCommand(String),
+    /// Call the named org.freedesktop.login1.Manager sleep method
+    Logind(&'static str),
     Skip(String),
 }
 
@@ -15,11 +17,11 @@ pub enum ActionRequest {
 pub async fn prepare_action(action: &IdleActionBlock) -> Vec<ActionRequest> {
     let cmd = action.command.clone();
     match action.kind {
-        IdleAction::Suspend => {
+        ref kind if kind.is_sleep() => {
             if !cmd.trim().is_empty() {
                 vec![ActionRequest::RunCommand(cmd)]
             } else {
-                vec![]
+                kind.logind_method().map(ActionRequest::Logind).into_iter().collect()
             }
         }
         IdleAction::LockScreen => {
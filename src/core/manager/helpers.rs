@@ -1,34 +1,115 @@
 use std::fs;
 use std::path::Path;
+use std::time::Duration;
 use tokio::process::Command;
 
 use crate::log::{log_error_message, log_message};
+use crate::core::services::{dbus::{logind_sleep, session_proxy}, wayland::set_output_power};
 
 use crate::{
-    config::model::IdleActionBlock, 
+    config::model::{BacklightTarget, IdleAction, IdleActionBlock}, 
     core::manager::{
         actions::{is_process_running, prepare_action, run_command_detached, run_command_silent, ActionRequest}, 
-        state::ManagerState, Manager,
+        state::{Inhibitor, LogindInhibitor, ManagerState, SavedBrightness, MANUAL_INHIBITOR}, Manager,
     }
 };
 
+const FADE_STEP: Duration = Duration::from_millis(50);
+
 #[derive(Clone, Debug)]
-struct BrightnessState {
+struct BacklightDevice {
+    name: String,
     value: u32,
     max_brightness: u32,
-    device: String,
 }
 
-pub async fn capture_brightness(state: &mut ManagerState) -> Result<(), std::io::Error> {
-    // Try sysfs method first
-    if let Some(sys_brightness) = capture_sysfs_brightness() {
-        log_message(&format!("Captured brightness via sysfs: {}/{} on device '{}'", 
-            sys_brightness.value, sys_brightness.max_brightness, sys_brightness.device));
+/// Read light devices of a sysfs class, optionally only the named one.
+/// Without a name, "leds" is limited to keyboard backlights.
+fn read_light_devices(subsystem: &str, only: Option<&str>) -> Vec<BacklightDevice> {
+    let Ok(entries) = fs::read_dir(Path::new("/sys/class").join(subsystem)) else {
+        return Vec::new();
+    };
+
+    let mut devices: Vec<BacklightDevice> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let wanted = match only {
+                Some(device) => device == name,
+                None => subsystem != "leds" || name.ends_with("::kbd_backlight"),
+            };
+            if !wanted {
+                return None;
+            }
+            let read = |file: &str| -> Option<u32> {
+                fs::read_to_string(entry.path().join(file)).ok()?.trim().parse().ok()
+            };
+            Some(BacklightDevice {
+                value: read("brightness")?,
+                max_brightness: read("max_brightness")?,
+                name,
+            })
+        })
+        .collect();
+
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    devices
+}
+
+/// Writes backlight levels through logind's Session.SetBrightness, which
+/// doesn't need write access to sysfs, falling back to sysfs directly
+struct BacklightWriter {
+    session: Option<zbus::Proxy<'static>>,
+}
+
+impl BacklightWriter {
+    async fn new() -> Self {
+        let session = match session_proxy().await {
+            Ok(proxy) => Some(proxy),
+            Err(e) => {
+                log_message(&format!("logind session unavailable, writing backlight via sysfs: {}", e));
+                None
+            }
+        };
+        Self { session }
+    }
+
+    async fn set(&mut self, subsystem: &str, device: &str, value: u32) -> Result<(), std::io::Error> {
+        if let Some(session) = &self.session {
+            match session.call::<_, _, ()>("SetBrightness", &(subsystem, device, value)).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    log_error_message(&format!("SetBrightness failed, falling back to sysfs: {}", e));
+                    self.session = None;
+                }
+            }
+        }
+        fs::write(Path::new("/sys/class").join(subsystem).join(device).join("brightness"), value.to_string())
+    }
+}
+
+pub async fn capture_brightness(
+    state: &mut ManagerState,
+    subsystem: &'static str,
+    device: Option<&str>,
+) -> Result<(), std::io::Error> {
+    let devices = read_light_devices(subsystem, device);
+    if !devices.is_empty() {
+        for d in &devices {
+            log_message(&format!("Captured brightness via sysfs: {}/{} on {} device '{}'",
+                d.value, d.max_brightness, subsystem, d.name));
+        }
+        state.saved_brightness.extend(
+            devices
+                .into_iter()
+                .map(|d| SavedBrightness { subsystem, device: Some(d.name), value: d.value }),
+        );
+        return Ok(());
+    }
 
-        // Store the full u32 value - don't truncate!
-        state.previous_brightness = Some(sys_brightness.value);
-        state.max_brightness = Some(sys_brightness.max_brightness);
-        state.brightness_device = Some(sys_brightness.device);
+    // brightnessctl only stands in for the panel backlight
+    if subsystem != "backlight" {
+        log_message(&format!("No {} devices found to capture", subsystem));
         return Ok(());
     }
 
@@ -40,7 +121,7 @@ pub async fn capture_brightness(state: &mut ManagerState) -> Result<(), std::io:
                 .trim()
                 .parse::<u32>()
                 .unwrap_or(0);
-            state.previous_brightness = Some(val);
+            state.saved_brightness.push(SavedBrightness { subsystem, device: None, value: val });
             log_message(&format!("Captured brightness via brightnessctl: {}", val));
         }
         Ok(out) => {
@@ -54,78 +135,113 @@ pub async fn capture_brightness(state: &mut ManagerState) -> Result<(), std::io:
     Ok(())
 }
 
+/// Stop a running fade and put every captured device back to its old level
 pub async fn restore_brightness(state: &mut ManagerState) -> Result<(), std::io::Error> {
-    if let Some(level) = state.previous_brightness {
-        log_message(&format!("Attempting to restore brightness to {}", level));
-
-        // Try sysfs restore first if we have device info
-        if let (Some(device), Some(_max)) = (&state.brightness_device, state.max_brightness) {
-            if restore_sysfs_brightness_to_device(device, level).is_ok() {
-                log_message("Brightness restored via sysfs");
-                state.previous_brightness = None;
-                state.max_brightness = None;
-                state.brightness_device = None;
-                return Ok(());
-            }
-        }
+    for (_, fade) in state.brightness_fades.drain(..) {
+        fade.abort();
+        let _ = fade.await;
+    }
 
-        // Fallback to generic sysfs restore
-        if restore_sysfs_brightness(level).is_ok() {
-            log_message("Brightness restored via sysfs (generic)");
-        } else {
-            log_message("Falling back to brightnessctl for brightness restore");
-            if let Err(e) = Command::new("brightnessctl")
-                .arg("set")
-                .arg(level.to_string())
-                .output()
-                .await
-            {
-                log_error_message(&format!("Failed to restore brightness: {}", e));
+    if state.saved_brightness.is_empty() {
+        return Ok(());
+    }
+
+    let saved = std::mem::take(&mut state.saved_brightness);
+    let mut writer = None;
+
+    for entry in saved {
+        match entry.device {
+            Some(device) => {
+                if writer.is_none() {
+                    writer = Some(BacklightWriter::new().await);
+                }
+                if let Some(writer) = writer.as_mut() {
+                    match writer.set(entry.subsystem, &device, entry.value).await {
+                        Ok(()) => log_message(&format!("Brightness of '{}' restored to {}", device, entry.value)),
+                        Err(e) => log_error_message(&format!("Failed to restore brightness of '{}': {}", device, e)),
+                    }
+                }
+            }
+            None => {
+                log_message("Falling back to brightnessctl for brightness restore");
+                if let Err(e) = Command::new("brightnessctl")
+                    .arg("set")
+                    .arg(entry.value.to_string())
+                    .output()
+                    .await
+                {
+                    log_error_message(&format!("Failed to restore brightness: {}", e));
+                }
             }
         }
-
-        // Reset stored brightness
-        state.previous_brightness = None;
-        state.max_brightness = None;
-        state.brightness_device = None;
     }
+
     Ok(())
 }
 
-fn capture_sysfs_brightness() -> Option<BrightnessState> {
-    let base = Path::new("/sys/class/backlight");
-    let device_entry = fs::read_dir(base).ok()?.next()?;
-    let device = device_entry.ok()?.file_name().to_string_lossy().to_string();
-
-    let current = fs::read_to_string(base.join(&device).join("brightness")).ok()?;
-    let max = fs::read_to_string(base.join(&device).join("max_brightness")).ok()?;
-    
-    Some(BrightnessState {
-        value: current.trim().parse().ok()?,
-        max_brightness: max.trim().parse().ok()?,
-        device,
-    })
-}
+/// Dim the lights to the action's target, fading gradually if configured.
+/// The fade runs in the background so input can abort it via restore_brightness.
+async fn dim_lights(state: &mut ManagerState, subsystem: &'static str, target: &BacklightTarget) -> Result<(), String> {
+    // A later stage on the same lights takes over from any fade still running
+    state.brightness_fades.retain(|(s, fade)| {
+        if *s == subsystem {
+            fade.abort();
+        }
+        *s != subsystem
+    });
+
+    let devices = read_light_devices(subsystem, target.device.as_deref());
+    if devices.is_empty() {
+        return Err(match &target.device {
+            Some(device) => format!("{} device '{}' not found", subsystem, device),
+            None => format!("no {} devices found", subsystem),
+        });
+    }
 
-fn restore_sysfs_brightness_to_device(device: &str, value: u32) -> Result<(), std::io::Error> {
-    let base = Path::new("/sys/class/backlight");
-    let path = base.join(device).join("brightness");
-    fs::write(&path, value.to_string())?;
-    Ok(())
-}
+    // An idle stage only ever lowers brightness, lights already below the target stay put
+    let level = target.level as u64;
+    let fades: Vec<(String, i64, i64)> = devices.iter()
+        .map(|d| {
+            let from = d.value as i64;
+            let to = ((d.max_brightness as u64 * level / 100) as i64).min(from);
+            (d.name.clone(), from, to)
+        })
+        .filter(|(_, from, to)| to != from)
+        .collect();
+    if fades.is_empty() {
+        log_message(&format!("{} already at or below {}%, not dimming", subsystem, target.level));
+        return Ok(());
+    }
 
-fn restore_sysfs_brightness(value: u32) -> Result<(), std::io::Error> {
-    let base = Path::new("/sys/class/backlight");
+    let steps = (target.fade.as_millis() / FADE_STEP.as_millis()).clamp(1, u32::MAX as u128) as u32;
+    let mut writer = BacklightWriter::new().await;
 
-    let entry = fs::read_dir(base)
-        .ok()
-        .and_then(|mut it| it.next())
-        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "No backlight device found"))??;
+    log_message(&format!(
+        "Dimming {} {} device(s) to {}% over {}s",
+        fades.len(), subsystem, target.level, target.fade.as_secs()
+    ));
 
-    let device = entry.file_name().to_string_lossy().to_string();
-    let path = base.join(device).join("brightness");
-    fs::write(&path, value.to_string())?;
+    let fade = tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(FADE_STEP);
+        let mut written: Vec<u32> = fades.iter().map(|(_, from, _)| *from as u32).collect();
 
+        for step in 1..=steps as i64 {
+            ticker.tick().await;
+            for ((name, from, to), last) in fades.iter().zip(written.iter_mut()) {
+                let value = (from + (to - from) * step / steps as i64) as u32;
+                if value == *last {
+                    continue;
+                }
+                if let Err(e) = writer.set(subsystem, name, value).await {
+                    log_error_message(&format!("Failed to set brightness of '{}': {}", name, e));
+                    return;
+                }
+                *last = value;
+            }
+        }
+    });
+
+    state.brightness_fades.push((subsystem, fade));
     Ok(())
 }
 
@@ -147,30 +263,41 @@ pub fn set_compositor_manager(state: &mut ManagerState, value: bool) {
 }
 
 pub fn get_manual_inhibit(state: &mut ManagerState) -> bool {
-    state.manually_paused
+    state.is_inhibited_by(MANUAL_INHIBITOR)
 }
 
 pub async fn set_manual_inhibit(mgr: &mut Manager, inhibit: bool) {
     if inhibit {
-        // Enable manual pause
         mgr.pause(true).await;
-        mgr.state.manually_paused = true;
     } else {
-        // Disable manual pause
         mgr.resume(true).await;
-        mgr.state.manually_paused = false;
     }
 }
 
 pub async fn run_action(mgr: &mut Manager, action: &IdleActionBlock) {
+    if let Err(e) = try_run_action(mgr, action).await {
+        log_error_message(&format!("Action '{}' failed: {}", action.name, e));
+    }
+}
+
+/// Run an action, returning failures that can be detected synchronously
+/// (native dpms and dimming); shell commands and logind sleep calls still
+/// report in the background
+pub async fn try_run_action(mgr: &mut Manager, action: &IdleActionBlock) -> Result<(), String> {
     log_message(&format!(
-        "Action triggered: name=\"{}\" kind={:?} timeout={} command=\"{}\"",
-        action.name, action.kind, action.timeout, action.command
+        "Action triggered: name=\"{}\" kind={:?} timeout={} command={}",
+        action.name, action.kind, action.timeout, action.command_label()
     ));
 
-    // Brightness capture
-    if matches!(action.kind, crate::config::model::IdleAction::Brightness) && mgr.state.previous_brightness.is_none() {
-        let _ = capture_brightness(&mut mgr.state).await;
+    // Brightness capture, then native dimming when a level is configured
+    if let Some(subsystem) = action.kind.light_subsystem() {
+        let device = action.backlight.as_ref().and_then(|t| t.device.as_deref());
+        if !mgr.state.saved_brightness.iter().any(|s| s.subsystem == subsystem) {
+            let _ = capture_brightness(&mut mgr.state, subsystem, device).await;
+        }
+        if let Some(target) = &action.backlight {
+            return dim_lights(&mut mgr.state, subsystem, target).await;
+        }
     }
 
     if matches!(action.kind, crate::config::model::IdleAction::LockScreen) {
@@ -179,8 +306,8 @@ pub async fn run_action(mgr: &mut Manager, action: &IdleActionBlock) {
         log_message("Lock screen action triggered, notifying lock watcher");
     }
 
-    // Handle pre-suspend for Suspend actions
-    if matches!(action.kind, crate::config::model::IdleAction::Suspend) {
+    // Handle pre-suspend for all sleep actions
+    if action.kind.is_sleep() {
         if let Some(cfg) = &mgr.state.cfg {
             if let Some(ref cmd) = cfg.pre_suspend_command {
                 log_message(&format!("Running pre-suspend command: {}", cmd));
@@ -202,15 +329,51 @@ pub async fn run_action(mgr: &mut Manager, action: &IdleActionBlock) {
         }
     }
 
+    // Prefer native output power control, the command is only a fallback
+    if action.kind == IdleAction::Dpms {
+        match set_output_power(false).await {
+            Ok(true) => {
+                mgr.state.outputs_powered_off = true;
+                log_message("Outputs powered off via wlr-output-power-management");
+                return Ok(());
+            }
+            Ok(false) if action.command.trim().is_empty() => {
+                return Err("compositor lacks wlr-output-power-management and no command is configured".into());
+            }
+            Ok(false) => log_message("wlr-output-power-management unavailable, running dpms command"),
+            Err(e) if action.command.trim().is_empty() => return Err(e.to_string()),
+            Err(e) => log_error_message(&format!("Native dpms failed, running command instead: {}", e)),
+        }
+    }
+
     let requests = prepare_action(action).await;
     for req in requests {
         match req {
             ActionRequest::RunCommand(cmd) => {
                 run_command_for_action(mgr, action, cmd).await;
             }
+            ActionRequest::Logind(method) => {
+                spawn_logind_sleep(&mgr.state, method)?;
+            }
             ActionRequest::Skip(_) => {}
         }
     }
+
+    Ok(())
+}
+
+/// Ask logind to sleep from a task of its own, so the manager lock isn't
+/// held across the bus round trips; failures are logged from there
+pub fn spawn_logind_sleep(state: &ManagerState, method: &'static str) -> Result<(), String> {
+    let Some(connection) = state.system_bus.clone() else {
+        return Err("no system bus connection for logind".to_string());
+    };
+    tokio::spawn(async move {
+        if let Err(e) = logind_sleep(&connection, method).await {
+            log_error_message(&format!("{} via logind failed: {}", method, e));
+        }
+    });
+    Ok(())
 }
 
 pub async fn run_command_for_action(mgr: &mut Manager, action: &IdleActionBlock, cmd: String) {
@@ -261,23 +424,10 @@ pub async fn lock_still_active(state: &ManagerState) -> bool {
 pub async fn trigger_all_idle_actions(mgr: &mut Manager) {
     use crate::config::model::IdleAction;
 
-    let block_name = if !mgr.state.ac_actions.is_empty() || !mgr.state.battery_actions.is_empty() {
-        match mgr.state.on_battery() {
-            Some(true) => "battery",
-            Some(false) => "ac",
-            None => "default",
-        }
-    } else {
-        "default"
-    };
+    let block_name = mgr.state.current_block.clone();
 
     // Clone the actions so we don't borrow mgr mutably while iterating
-    let actions_to_trigger: Vec<IdleActionBlock> = match block_name {
-        "ac" => mgr.state.ac_actions.clone(),
-        "battery" => mgr.state.battery_actions.clone(),
-        "default" => mgr.state.default_actions.clone(),
-        _ => unreachable!(),
-    };
+    let actions_to_trigger: Vec<IdleActionBlock> = mgr.state.get_active_actions().to_vec();
 
     if actions_to_trigger.is_empty() {
         log_message("No actions defined to trigger");
@@ -299,12 +449,7 @@ pub async fn trigger_all_idle_actions(mgr: &mut Manager) {
 
     // Now update `last_triggered` after all actions are done
     let now = std::time::Instant::now();
-    let actions_mut: &mut Vec<IdleActionBlock> = match block_name {
-        "ac" => &mut mgr.state.ac_actions,
-        "battery" => &mut mgr.state.battery_actions,
-        "default" => &mut mgr.state.default_actions,
-        _ => unreachable!(),
-    };
+    let actions_mut = mgr.state.get_active_actions_mut();
 
     for a in actions_mut.iter_mut() {
         a.last_triggered = Some(now);
@@ -314,73 +459,187 @@ pub async fn trigger_all_idle_actions(mgr: &mut Manager) {
     log_message("All idle actions triggered manually");
 }
 
-pub async fn incr_active_inhibitor(mgr: &mut Manager) {
-    let prev = mgr.state.active_inhibitor_count;
-    mgr.state.active_inhibitor_count = prev.saturating_add(1);
-    let now = mgr.state.active_inhibitor_count;
-
-    if prev == 0 {
-        if !mgr.state.manually_paused {
-            mgr.state.paused = true;
-            log_message(&format!(
-                "Inhibitor registered (count: {} → {}): first inhibitor active → idle timers paused",
-                prev, now
-            ));
-        } else {
-            log_message(&format!(
-                "Inhibitor registered (count: {} → {}): manual pause already active",
-                prev, now
-            ));
+/// Switch to a named profile, or back to the regular blocks with `None`
+pub async fn set_active_profile(mgr: &mut Manager, name: Option<&str>) -> Result<(), String> {
+    if let Some(name) = name {
+        let exists = mgr.state.cfg.as_ref().is_some_and(|c| c.profiles.iter().any(|p| p.name == name));
+        if !exists {
+            let available = mgr.state.cfg.as_ref()
+                .map(|c| c.profiles.iter().map(|p| p.name.clone()).collect::<Vec<_>>())
+                .unwrap_or_default();
+            return Err(if available.is_empty() {
+                format!("Profile '{}' not found. No profiles are configured", name)
+            } else {
+                format!("Profile '{}' not found. Available profiles: {}", name, available.join(", "))
+            });
         }
-    } else {
+    }
+
+    let previous = mgr.state.active_profile.clone();
+    mgr.state.set_profile(name.map(str::to_string));
+    log_message(&format!(
+        "Profile switched: {} -> {}",
+        previous.as_deref().unwrap_or("none"),
+        name.unwrap_or("none")
+    ));
+
+    sync_profile_inhibitor(mgr).await;
+    mgr.recheck_media().await;
+
+    // Start the new action list from a clean slate
+    mgr.reset().await;
+    mgr.trigger_instant_actions().await;
+    Ok(())
+}
+
+/// Hold or release the inhibitor of the active profile to match its `inhibit` setting
+pub async fn sync_profile_inhibitor(mgr: &mut Manager) {
+    let inhibitor = mgr.state.active_profile()
+        .filter(|p| p.inhibit)
+        .map(|p| Inhibitor::all(format!("profile {}", p.name)));
+    set_inhibitor(mgr, "profile", inhibitor).await;
+}
+
+/// Replace the known logind idle locks, logging holders that come and go
+pub async fn set_logind_inhibitors(mgr: &mut Manager, inhibitors: Vec<LogindInhibitor>) {
+    for added in inhibitors.iter().filter(|i| !mgr.state.logind_inhibitors.contains(i)) {
         log_message(&format!(
-            "Inhibitor registered (count: {} → {})",
-            prev, now
+            "logind idle inhibitor taken by {} (pid {}): {}",
+            added.who, added.pid, added.why
         ));
     }
+    for removed in mgr.state.logind_inhibitors.iter().filter(|i| !inhibitors.contains(i)) {
+        log_message(&format!("logind idle inhibitor released by {} (pid {})", removed.who, removed.pid));
+    }
 
-    // wake idle task so it can recalc next timeout (if needed)
-    mgr.state.notify.notify_one();
+    mgr.state.logind_inhibitors = inhibitors;
+    sync_logind_inhibitor(mgr).await;
 }
 
-pub async fn decr_active_inhibitor(mgr: &mut Manager) {
-    let prev = mgr.state.active_inhibitor_count;
+/// Hold one inhibitor while any logind idle lock is active and respected
+pub async fn sync_logind_inhibitor(mgr: &mut Manager) {
+    let respect = mgr.state.cfg.as_ref().is_none_or(|c| c.respect_logind_inhibitors);
+    let wanted = respect && !mgr.state.logind_inhibitors.is_empty();
+    let scope = mgr.state.cfg.as_ref().map(|c| c.inhibit_scopes.logind.clone()).unwrap_or_default();
+    let holders = mgr.state.logind_inhibitors.iter()
+        .map(|i| format!("{}: {}", i.who, i.why))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    set_inhibitor(mgr, "logind", wanted.then(|| Inhibitor::new(scope, holders))).await;
+}
+
+/// Follow the compositor's own idle inhibition (e.g. a fullscreen video surface)
+pub async fn set_wayland_inhibited(mgr: &mut Manager, inhibited: bool) {
+    if inhibited == mgr.state.is_inhibited_by("wayland") {
+        return;
+    }
+
+    log_message(if inhibited {
+        "Compositor is inhibiting idle"
+    } else {
+        "Compositor stopped inhibiting idle"
+    });
 
-    if prev == 0 {
-        log_message("decr_active_inhibitor called but count already 0 (possible mismatch)");
+    let inhibitor = inhibited.then(|| Inhibitor::all("idle-inhibit surface"));
+    set_inhibitor(mgr, "wayland", inhibitor).await;
+}
+
+/// Evaluate the schedule at the current local time and apply the rules in effect
+pub async fn apply_schedule(mgr: &mut Manager) {
+    let Some(cfg) = mgr.state.cfg.clone() else {
         return;
+    };
+
+    let now = chrono::Local::now().naive_local();
+    let active: Vec<_> = cfg.schedule.iter().filter(|r| r.is_active_at(now)).collect();
+    let names: Vec<String> = active.iter().map(|r| r.name.clone()).collect();
+
+    if names != mgr.state.schedule_active {
+        log_message(&format!(
+            "Schedule changed: [{}] -> [{}]",
+            mgr.state.schedule_active.join(", "),
+            names.join(", ")
+        ));
+        mgr.state.schedule_active = names;
     }
 
-    mgr.state.active_inhibitor_count = prev.saturating_sub(1);
-    let now = mgr.state.active_inhibitor_count;
+    // The first matching rule wins for profile and block selection
+    let profile = active.iter().find_map(|r| r.profile.clone());
+    let block = active.iter().find_map(|r| r.block.clone());
+    let inhibiting: Vec<&str> = active.iter().filter(|r| r.inhibit).map(|r| r.name.as_str()).collect();
 
-    if now == 0 {
-        if !mgr.state.manually_paused {
-            mgr.state.paused = false;
+    if profile != mgr.state.scheduled_profile || block != mgr.state.scheduled_block {
+        mgr.state.scheduled_profile = profile;
+        mgr.state.scheduled_block = block;
+        mgr.state.refresh_profile();
+
+        sync_profile_inhibitor(mgr).await;
+        mgr.recheck_media().await;
+        // Only fires if refresh_profile switched to another block
+        mgr.trigger_instant_actions().await;
+    }
+
+    let inhibitor = (!inhibiting.is_empty()).then(|| Inhibitor::all(format!("schedule {}", inhibiting.join(", "))));
+    set_inhibitor(mgr, "schedule", inhibitor).await;
+}
+
+
+/// Hold (`Some`) or release (`None`) the inhibitor of one source. An `All`
+/// scope pauses the idle timers; any other only holds back the actions it
+/// names, and actions skipped meanwhile run once nothing blocks them.
+pub async fn set_inhibitor(mgr: &mut Manager, source: &str, inhibitor: Option<Inhibitor>) {
+    let previous = mgr.state.inhibitors.get(source).map(|i| i.scope.clone());
+
+    match inhibitor {
+        None if previous.is_none() => return,
+        None => {
+            mgr.state.inhibitors.remove(source);
+            log_message(&format!("Inhibitor {} released", source));
+        }
+        Some(inhibitor) => {
+            if previous.as_ref() == Some(&inhibitor.scope) {
+                // Still held; keep when it started, just follow reason and expiry
+                if let Some(held) = mgr.state.inhibitors.get_mut(source) {
+                    held.reason = inhibitor.reason;
+                    held.expires = inhibitor.expires;
+                }
+                return;
+            }
             log_message(&format!(
-                "Inhibitor removed (count: {} → {}): no more inhibitors → idle timers resumed",
-                prev, now
+                "Inhibitor {} active ({}), blocking {}",
+                source, inhibitor.reason, inhibitor.scope
             ));
+            mgr.state.inhibitors.insert(source.to_string(), inhibitor);
+        }
+    }
 
-            // fire resume commands queued (if any)
-            mgr.fire_resume_queue().await;
+    // A suspend keeps the timers stopped until resume, whatever comes or goes
+    let paused = mgr.state.suspend_paused || mgr.state.inhibits_all();
+    if paused != mgr.state.paused {
+        mgr.state.paused = paused;
+        if paused {
+            log_message("Idle timers paused by inhibitor");
         } else {
-            log_message(&format!(
-                "Inhibitor removed (count: {} → {}): manual pause still active, timers remain paused",
-                prev, now
-            ));
+            log_message("No inhibitor blocks every action any more, idle timers resumed");
+            mgr.fire_resume_queue().await;
         }
+    }
 
-        // wake idle task so timeouts will be recalculated right away
-        mgr.state.notify.notify_one();
-    } else {
-        log_message(&format!(
-            "Inhibitor removed (count: {} → {})",
-            prev, now
-        ));
+    // A dropped or narrower scope may release skipped actions
+    if previous.is_some() {
+        mgr.run_skipped_actions().await;
     }
+    mgr.state.notify.notify_one();
+}
+
+/// Hold the media inhibitor while something plays, with the configured scope
+pub async fn set_media_playing(mgr: &mut Manager, playing: bool) {
+    let scope = mgr.state.cfg.as_ref().map(|c| c.inhibit_scopes.media.clone()).unwrap_or_default();
+    set_inhibitor(mgr, "media", playing.then(|| Inhibitor::new(scope, "media playing"))).await;
 }
 
+
 pub async fn trigger_pre_suspend(mgr: &mut Manager) {
     if let Some(cmd) = &mgr.state.pre_suspend_command {
         log_message(&format!("Running pre-suspend command: {}", cmd));
@@ -53,7 +53,7 @@ pub fn spawn_idle_task(manager: Arc<Mutex<Manager>>) -> JoinHandle<()> {
 
             // Now check timeouts only once after wake
             let mut mgr = manager.lock().await;
-            if !mgr.state.paused && !mgr.state.manually_paused {
+            if !mgr.state.paused {
                 mgr.check_timeouts().await;
             }
         }
@@ -122,9 +122,12 @@ pub async fn spawn_lock_watcher(manager: Arc<Mutex<Manager>>) -> JoinHandle<()>
                         break;  // Already unlocked, don't do it again
                     }
 
-                    if let Some(lock_action) = mgr.state.default_actions.iter()
+                    if let Some(lock_action) = mgr.state.profile_actions.iter()
+                        .chain(mgr.state.default_actions.iter())
                         .chain(mgr.state.ac_actions.iter())
                         .chain(mgr.state.battery_actions.iter())
+                        .chain(mgr.state.battery_low_actions.iter())
+                        .chain(mgr.state.battery_critical_actions.iter())
                         .find(|a| matches!(a.kind, crate::config::model::IdleAction::LockScreen))
                     {
                         if let Some(resume_cmd) = &lock_action.resume_command {
@@ -12,11 +12,13 @@ use tokio::{
 
 pub use self::state::ManagerState;
 use crate::{
-    config::model::{IdleAction, StasisConfig}, 
+    config::model::{IdleAction, IdleActionBlock, InhibitScope, StasisConfig}, 
     core::manager::{
         actions::{is_process_running, run_command_detached},
-        helpers::{restore_brightness, run_action},
+        helpers::{restore_brightness, run_action, set_inhibitor},
+        state::{Inhibitor, MANUAL_INHIBITOR},
     }, 
+    core::services::wayland::set_output_power,
     log::log_message
 };
 
@@ -72,23 +74,47 @@ impl Manager {
         };
 
         // Restore brightness if needed
-        if self.state.previous_brightness.is_some() {
+        if !self.state.saved_brightness.is_empty() || !self.state.brightness_fades.is_empty() {
             if let Err(e) = restore_brightness(&mut self.state).await {
                 log_message(&format!("Failed to restore brightness: {}", e));
             }
         }
+
+        // Power outputs back on if dpms turned them off natively
+        if self.state.outputs_powered_off {
+            match set_output_power(true).await {
+                Ok(_) => {
+                    self.state.outputs_powered_off = false;
+                    // The native path already undid dpms, skip its resume commands
+                    self.state.resume_queue.retain(|a| a.kind != IdleAction::Dpms);
+                    log_message("Outputs powered on");
+                }
+                Err(e) => log_message(&format!("Failed to power outputs on: {}", e)),
+            }
+        }
         
         let now = Instant::now();
         let debounce = Duration::from_secs(cfg.debounce_seconds as u64);
         self.state.debounce = Some(now + debounce);
         self.state.last_activity = now;
 
+        // Restart the compositor's stage timers along with ours
+        self.state.compositor_stage = None;
+        self.state.stages_changed.notify_one();
+
         // Store values we need before borrowing
         let is_locked = self.state.lock_state.is_locked;
         let cmd_to_check = self.state.lock_state.command.clone();
 
         // Clear only actions that are before or equal to the current stage
-        for actions in [&mut self.state.default_actions, &mut self.state.ac_actions, &mut self.state.battery_actions] {
+        for actions in [
+            &mut self.state.default_actions,
+            &mut self.state.ac_actions,
+            &mut self.state.battery_actions,
+            &mut self.state.battery_low_actions,
+            &mut self.state.battery_critical_actions,
+            &mut self.state.profile_actions,
+        ] {
             let mut past_lock = false;
             for a in actions.iter_mut() {
                 if matches!(a.kind, crate::config::model::IdleAction::LockScreen) {
@@ -99,6 +125,7 @@ impl Manager {
                     continue;
                 }
                 a.last_triggered = None;
+                a.skipped = false;
             }
         }
 
@@ -168,7 +195,7 @@ impl Manager {
 
     // Check whether we have been idle enough to elapse one of the timeouts
     pub async fn check_timeouts(&mut self) {
-        if self.state.paused || self.state.manually_paused {
+        if self.state.paused {
             return;
         }
 
@@ -179,6 +206,8 @@ impl Manager {
         let is_locked = self.state.lock_state.is_locked;
         let last_activity = self.state.last_activity;
         let debounce = self.state.debounce;
+        let compositor_managed = self.state.compositor_managed;
+        let compositor_stage = self.state.compositor_stage;
 
         // Get reference to the right actions Vec using helper method
         let actions = self.state.get_active_actions_mut();
@@ -199,14 +228,18 @@ impl Manager {
         let next_fire = if let Some(last_trig) = actions[index].last_triggered {
             // Already triggered: timeout from when it last fired
             last_trig + timeout
-        } else if index > 0 {
-            // Not first action: fire relative to previous action
-            if let Some(prev_trig) = actions[index - 1].last_triggered {
-                prev_trig + timeout
-            } else {
-                // Previous hasn't fired yet, shouldn't happen but fallback
-                last_activity + timeout
+        } else if compositor_managed {
+            // The compositor tells us when each stage is reached
+            if compositor_stage.is_none_or(|reached| reached < index) {
+                return;
             }
+            now
+        } else if let Some(prev_trig) = index.checked_sub(1).and_then(|i| actions[i].last_triggered) {
+            // Not first action: fire relative to previous action
+            prev_trig + timeout
+        } else if index > 0 {
+            // Previous hasn't fired yet, shouldn't happen but fallback
+            last_activity + timeout
         } else {
             // First action: apply debounce + timeout from last_activity
             let base = debounce.unwrap_or(last_activity);
@@ -226,6 +259,11 @@ impl Manager {
             (action_clone, actions.len())
         }; // Borrow ends here
 
+        let blocked_by = self.blocked_by(&action_clone).map(str::to_string);
+        if blocked_by.is_some() {
+            self.state.get_active_actions_mut()[index].skipped = true;
+        }
+
         // Advance index
         self.state.action_index += 1;
         if self.state.action_index < actions_len {
@@ -235,6 +273,12 @@ impl Manager {
             self.state.action_index = actions_len - 1;
         }
 
+        // Later stages keep counting from here as if it had fired
+        if let Some(source) = blocked_by {
+            log_message(&format!("Skipping action '{}': blocked by {}", action_clone.name, source));
+            return;
+        }
+
         // Add to resume queue if needed
         if !matches!(action_clone.kind, IdleAction::LockScreen) && action_clone.resume_command.is_some() {
             self.state.resume_queue.push(action_clone.clone());
@@ -265,7 +309,7 @@ impl Manager {
 
 
     pub fn next_action_instant(&self) -> Option<Instant> {
-        if self.state.paused || self.state.manually_paused {
+        if self.state.paused {
             return None;
         }
 
@@ -289,14 +333,15 @@ impl Manager {
             let next_time = if let Some(last_trig) = action.last_triggered {
                 // Already triggered: timeout from when it last fired
                 last_trig + timeout
-            } else if i > 0 {
+            } else if self.state.compositor_managed {
+                // The compositor's stage notification will wake us
+                continue;
+            } else if let Some(prev_trig) = i.checked_sub(1).and_then(|p| actions[p].last_triggered) {
                 // Not first action: fire relative to previous action
-                if let Some(prev_trig) = actions[i - 1].last_triggered {
-                    prev_trig + timeout
-                } else {
-                    // Previous hasn't fired yet, shouldn't happen but fallback
-                    self.state.last_activity + timeout
-                }
+                prev_trig + timeout
+            } else if i > 0 {
+                // Previous hasn't fired yet, shouldn't happen but fallback
+                self.state.last_activity + timeout
             } else {
                 // First action: use debounce + timeout
                 let base = self.state.debounce.unwrap_or(self.state.last_activity);
@@ -314,6 +359,46 @@ impl Manager {
 
 
 
+    /// The scoped inhibitor holding back this action, if any
+    pub fn blocked_by(&self, action: &IdleActionBlock) -> Option<&str> {
+        self.state.inhibitors
+            .iter()
+            .find(|(_, inhibitor)| inhibitor.scope.blocks(action))
+            .map(|(source, _)| source.as_str())
+    }
+
+    /// Run actions that were skipped for a scoped inhibitor that has since
+    /// gone, in stage order, as long as the user is still idle
+    pub async fn run_skipped_actions(&mut self) {
+        if self.state.paused {
+            return;
+        }
+
+        let due: Vec<usize> = self.state.get_active_actions()
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| a.skipped && self.blocked_by(a).is_none())
+            .map(|(i, _)| i)
+            .collect();
+
+        for index in due {
+            let action = {
+                let actions = self.state.get_active_actions_mut();
+                actions[index].skipped = false;
+                actions[index].clone()
+            };
+            if matches!(action.kind, IdleAction::LockScreen) && self.state.lock_state.is_locked {
+                continue;
+            }
+
+            log_message(&format!("Running previously skipped action '{}'", action.name));
+            if !matches!(action.kind, IdleAction::LockScreen) && action.resume_command.is_some() {
+                self.state.resume_queue.push(action.clone());
+            }
+            run_action(self, &action).await;
+        }
+    }
+
     pub async fn advance_past_lock(&mut self) {
         log_message("Advancing state past lock stage...");
         self.state.lock_state.post_advanced = true;
@@ -322,33 +407,35 @@ impl Manager {
 
     pub async fn pause(&mut self, manual: bool) {
         if manual {
-            self.state.manually_paused = true;
-            log_message("Idle timers manually paused");
-        } else if !self.state.manually_paused {
-            self.state.paused = true;
-            log_message("Idle timers automatically paused");
+            set_inhibitor(self, MANUAL_INHIBITOR, Some(Inhibitor::all("paused via IPC"))).await;
+        } else {
+            self.state.suspend_paused = true;
+            if !self.state.paused {
+                self.state.paused = true;
+                log_message("Idle timers automatically paused");
+            }
         }
     }
 
     pub async fn resume(&mut self, manually: bool) {
         if manually {
-            if self.state.manually_paused {
-                self.state.manually_paused = false;
-                
-                if self.state.active_inhibitor_count == 0 {
-                    self.state.paused = false;
-                    log_message("Idle timers manually resumed");
-                } else {
+            // Inhibits from `stasis inhibit` are left to `stasis uninhibit`
+            if self.state.manually_paused() {
+                set_inhibitor(self, MANUAL_INHIBITOR, None).await;
+                if self.state.paused {
                     log_message(&format!(
                         "Manual pause cleared, but {} inhibitor(s) still active - timers remain paused",
-                        self.state.active_inhibitor_count
+                        self.state.inhibitors.values().filter(|i| i.scope == InhibitScope::All).count()
                     ));
                 }
             }
-        } else if !self.state.manually_paused && self.state.paused {
-            // This is called by decr_active_inhibitor when count reaches 0
-            self.state.paused = false;
-            log_message("Idle timers automatically resumed");
+        } else {
+            // After a suspend; inhibitors that block everything keep the pause
+            self.state.suspend_paused = false;
+            if self.state.paused && !self.state.inhibits_all() {
+                self.state.paused = false;
+                log_message("Idle timers automatically resumed");
+            }
         }
     }
 
@@ -368,17 +455,13 @@ impl Manager {
         };
 
         // sync check (pactl + mpris). This is blocking but fine here.
-        let playing = crate::core::services::media::check_media_playing(ignore_remote, &media_blacklist);
-
-        // Only change state via the helpers so behaviour stays consistent:
-        if playing && !self.state.media_playing {
-            // call the same helper the monitor uses
-            crate::core::manager::helpers::incr_active_inhibitor(self).await;
-            self.state.media_playing = true;
-        } else if !playing && self.state.media_playing {
-            crate::core::manager::helpers::decr_active_inhibitor(self).await;
-            self.state.media_playing = false;
-        }
+        // Skipped entirely when the active profile turns media monitoring off
+        let playing = self.state.monitor_media()
+            && crate::core::services::media::check_media_playing(ignore_remote, &media_blacklist);
+
+        // Only change state via the helper the monitor uses, so behaviour stays
+        // consistent; it also picks up a changed scope after a reload
+        crate::core::manager::helpers::set_media_playing(self, playing).await;
     }
 
     pub async fn shutdown(&mut self) {
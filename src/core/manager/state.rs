@@ -1,9 +1,9 @@
-use std::{sync::Arc, time::{Duration, Instant}};
+use std::{collections::BTreeMap, sync::Arc, time::{Duration, Instant, SystemTime}};
 
-use tokio::sync::Notify;
+use tokio::{sync::Notify, task::JoinHandle};
 
 use crate::{
-    config::model::{IdleAction, IdleActionBlock, StasisConfig}, log::log_message
+    config::{model::{AppInhibitPattern, IdleAction, IdleActionBlock, InhibitScope, Profile, StasisConfig}, ConfigSource}, log::log_message
 };
 use crate::core::utils::{detect_chassis, ChassisKind};
 
@@ -12,32 +12,52 @@ pub struct ManagerState {
     pub ac_actions: Vec<IdleActionBlock>,
     pub action_index: usize,
     pub active_flags: ActiveFlags,
-    pub active_inhibitor_count: u32,
+    pub active_profile: Option<String>,
     pub app_inhibit_debounce: Option<Instant>,
     pub battery_actions: Vec<IdleActionBlock>,
-    pub brightness_device: Option<String>,
+    pub battery_critical_actions: Vec<IdleActionBlock>,
+    pub battery_low_actions: Vec<IdleActionBlock>,
+    pub brightness_fades: Vec<(&'static str, JoinHandle<()>)>,
     pub cfg: Option<Arc<StasisConfig>>,
     pub chassis: ChassisType, 
+    /// Idle timers are driven by ext-idle-notify rather than libinput
     pub compositor_managed: bool,
+    /// Furthest stage (action index) the compositor reported reached since
+    /// its stage notifications were last created
+    pub compositor_stage: Option<usize>,
+    pub config_source: ConfigSource,
     pub current_block: String,
     pub debounce: Option<Instant>,
     pub default_actions: Vec<IdleActionBlock>,
+    /// Everything holding back idle actions, keyed by source
+    /// (`media`, `logind`, `screensaver.3`, `inhibit_rules.zoom`, ...)
+    pub inhibitors: BTreeMap<String, Inhibitor>,
     pub instants_triggered: bool,
     pub last_activity: Instant,
     pub lock_state: LockState,
     pub lock_notify: Arc<Notify>,
-    pub manually_paused: bool,
-    pub max_brightness: Option<u32>,
-    pub media_playing: bool,
+    pub logind_inhibitors: Vec<LogindInhibitor>,
     pub notify: Arc<Notify>,
+    pub outputs_powered_off: bool,
+    /// Timers are stopped, by an inhibitor blocking every action or a suspend
     pub paused: bool,
-    pub previous_brightness: Option<u32>,
     pub pre_suspend_command: Option<String>,
+    pub profile_actions: Vec<IdleActionBlock>,
     pub resume_queue: Vec<IdleActionBlock>,
     pub resume_commands_fired: bool,
+    pub saved_brightness: Vec<SavedBrightness>,
+    pub schedule_active: Vec<String>,
+    pub scheduled_block: Option<String>,
+    pub scheduled_profile: Option<String>,
     pub shutdown_flag: Arc<Notify>,
+    /// Wakes the Wayland loop to recreate per-stage idle notifications
+    pub stages_changed: Arc<Notify>,
     pub start_time: Instant,
     pub suspend_occured: bool,
+    /// Timers are stopped between PrepareForSleep and resume, whatever inhibitors do
+    pub suspend_paused: bool,
+    /// Shared system bus connection, for logind calls made on behalf of actions
+    pub system_bus: Option<zbus::Connection>,
 }
 
 impl Default for ManagerState {
@@ -48,64 +68,61 @@ impl Default for ManagerState {
             ac_actions: Vec::new(),
             action_index: 0,
             active_flags: ActiveFlags::default(),
-            active_inhibitor_count: 0,
+            active_profile: None,
             app_inhibit_debounce: None,
             battery_actions: Vec::new(),
-            brightness_device: None,
+            battery_critical_actions: Vec::new(),
+            battery_low_actions: Vec::new(),
+            brightness_fades: Vec::new(),
             cfg: None,
             chassis: ChassisType::Desktop(DesktopState),
             compositor_managed: false,
+            compositor_stage: None,
+            config_source: ConfigSource::default(),
             current_block: "default".to_string(),
             debounce: None,
             default_actions: Vec::new(),
+            inhibitors: BTreeMap::new(),
             instants_triggered: false,
             last_activity: now, 
             lock_state: LockState::default(),
-            manually_paused: false,
-            max_brightness: None,
-            media_playing: false,
             notify: Arc::new(Notify::new()),
+            outputs_powered_off: false,
             lock_notify: Arc::new(Notify::new()),
+            logind_inhibitors: Vec::new(),
             paused: false,
-            previous_brightness: None,
             pre_suspend_command: None,
+            profile_actions: Vec::new(),
             resume_queue: Vec::new(),
             resume_commands_fired: false,
+            saved_brightness: Vec::new(),
+            schedule_active: Vec::new(),
+            scheduled_block: None,
+            scheduled_profile: None,
             shutdown_flag: Arc::new(Notify::new()),
+            stages_changed: Arc::new(Notify::new()),
             start_time: now,
             suspend_occured: false,
+            suspend_paused: false,
+            system_bus: None,
         }
     }
 }
 
 impl ManagerState {
     pub fn new(cfg: Arc<StasisConfig>) -> Self { 
-        let default_actions: Vec<_> = cfg
-            .actions
-            .iter()
-            .filter(|a| !a.name.starts_with("ac.") && !a.name.starts_with("battery."))
-            .cloned()
-            .collect();
-
-        let ac_actions: Vec<_> = cfg
-            .actions
-            .iter()
-            .filter(|a| a.name.starts_with("ac."))
-            .cloned()
-            .collect();
-
-        let battery_actions: Vec<_> = cfg
-            .actions
-            .iter()
-            .filter(|a| a.name.starts_with("battery."))
-            .cloned()
-            .collect();
+        let [default_actions, ac_actions, battery_actions, battery_low_actions, battery_critical_actions] =
+            split_actions(&cfg);
 
         let now = Instant::now();
         let debounce = Some(now + Duration::from_secs(cfg.debounce_seconds as u64));
 
         let chassis = match detect_chassis() {
-            ChassisKind::Laptop => ChassisType::Laptop(LaptopState { on_battery: false }),
+            ChassisKind::Laptop => ChassisType::Laptop(LaptopState {
+                on_battery: false,
+                capacity: None,
+                level: BatteryLevel::Normal,
+            }),
             ChassisKind::Desktop => ChassisType::Desktop(DesktopState),
         };
 
@@ -119,32 +136,43 @@ impl ManagerState {
             ac_actions,
             action_index: 0,
             active_flags: ActiveFlags::default(),
-            active_inhibitor_count: 0,
+            active_profile: None,
             app_inhibit_debounce: None,
             battery_actions,
-            brightness_device: None,
+            battery_critical_actions,
+            battery_low_actions,
+            brightness_fades: Vec::new(),
             cfg: Some(cfg.clone()),
             chassis,
             compositor_managed: false,
+            compositor_stage: None,
+            config_source: ConfigSource::default(),
             current_block,
             debounce,
             default_actions,
+            inhibitors: BTreeMap::new(),
             instants_triggered: false,
             last_activity: now,
             lock_state: LockState::from_config(&cfg),
-            manually_paused: false,
-            max_brightness: None,
-            media_playing: false,
             notify: Arc::new(Notify::new()),
+            outputs_powered_off: false,
             lock_notify: Arc::new(Notify::new()),
+            logind_inhibitors: Vec::new(),
             paused: false,
-            previous_brightness: None,
             pre_suspend_command: cfg.pre_suspend_command.clone(),
+            profile_actions: Vec::new(),
             resume_queue: Vec::new(),
             resume_commands_fired: false,
+            saved_brightness: Vec::new(),
+            schedule_active: Vec::new(),
+            scheduled_block: None,
+            scheduled_profile: None,
             shutdown_flag: Arc::new(Notify::new()),
+            stages_changed: Arc::new(Notify::new()),
             start_time: now,
             suspend_occured: false,
+            suspend_paused: false,
+            system_bus: None,
         };
 
         state
@@ -169,13 +197,163 @@ impl ManagerState {
         }
     }
 
-    /// Update current_block based on chassis type and power state
+    /// Name of the profile in effect: a manual choice wins over the schedule
+    pub fn effective_profile(&self) -> Option<&str> {
+        self.active_profile.as_deref().or(self.scheduled_profile.as_deref())
+    }
+
+    /// Config of the profile in effect, if any
+    pub fn active_profile(&self) -> Option<&Profile> {
+        let name = self.effective_profile()?;
+        self.cfg.as_ref()?.profiles.iter().find(|p| p.name == name)
+    }
+
+    pub fn is_inhibited_by(&self, source: &str) -> bool {
+        self.inhibitors.contains_key(source)
+    }
+
+    /// Whether an inhibitor blocking every action is held
+    pub fn inhibits_all(&self) -> bool {
+        self.inhibitors.values().any(|i| i.scope == InhibitScope::All)
+    }
+
+    /// Paused by `stasis pause` or toggle-inhibit, the state Waybar's toggle flips.
+    /// Inhibits from `stasis inhibit` are not counted; they show up as inhibitors.
+    pub fn manually_paused(&self) -> bool {
+        self.is_inhibited_by(MANUAL_INHIBITOR)
+    }
+
+    /// Whether media playback inhibits idle, honouring the active profile
+    pub fn monitor_media(&self) -> bool {
+        self.active_profile()
+            .and_then(|p| p.monitor_media)
+            .unwrap_or_else(|| self.cfg.as_ref().map(|c| c.monitor_media).unwrap_or(false))
+    }
+
+    /// App patterns that inhibit idle, honouring the active profile
+    pub fn inhibit_apps(&self) -> Vec<AppInhibitPattern> {
+        match self.active_profile().and_then(|p| p.inhibit_apps.as_ref()) {
+            Some(apps) => apps.clone(),
+            None => self.cfg.as_ref().map(|c| c.inhibit_apps.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// Switch to the named profile (None returns to the scheduled or regular blocks)
+    pub fn set_profile(&mut self, name: Option<String>) {
+        self.active_profile = name;
+        self.refresh_profile();
+    }
+
+    /// Reload the actions of the profile in effect and reselect the block
+    pub fn refresh_profile(&mut self) {
+        self.profile_actions = self
+            .active_profile()
+            .map(|p| p.actions.clone())
+            .unwrap_or_default();
+        self.update_current_block();
+    }
+
+    pub fn battery_capacity(&self) -> Option<u8> {
+        match &self.chassis {
+            ChassisType::Laptop(l) => l.capacity,
+            ChassisType::Desktop(_) => None,
+        }
+    }
+
+    pub fn battery_level(&self) -> Option<BatteryLevel> {
+        match &self.chassis {
+            ChassisType::Laptop(l) => Some(l.level),
+            ChassisType::Desktop(_) => None,
+        }
+    }
+
+    /// Record the battery charge and update the level, with hysteresis so a
+    /// charge hovering around a threshold doesn't flap between blocks
+    pub fn set_battery_capacity(&mut self, capacity: u8) {
+        let (low, critical, hysteresis) = match &self.cfg {
+            Some(cfg) => (cfg.battery_low_threshold, cfg.battery_critical_threshold, cfg.battery_hysteresis),
+            None => return,
+        };
+
+        if let ChassisType::Laptop(l) = &mut self.chassis {
+            l.capacity = Some(capacity);
+
+            let level = if capacity <= critical
+                || (l.level == BatteryLevel::Critical && capacity <= critical.saturating_add(hysteresis))
+            {
+                BatteryLevel::Critical
+            } else if capacity <= low
+                || (l.level != BatteryLevel::Normal && capacity <= low.saturating_add(hysteresis))
+            {
+                BatteryLevel::Low
+            } else {
+                BatteryLevel::Normal
+            };
+
+            if level != l.level {
+                log_message(&format!("Battery level: {} -> {} ({}%)", l.level, level, capacity));
+                l.level = level;
+                self.update_current_block();
+            }
+        }
+    }
+
+    /// Update current_block based on active profile, schedule, chassis type and power state
     pub fn update_current_block(&mut self) {
-        let new_block = match &self.chassis {
+        // A profile with its own actions takes precedence over the other blocks
+        let new_block = if !self.profile_actions.is_empty() {
+            format!("profile:{}", self.effective_profile().unwrap_or_default())
+        } else {
+            match self.scheduled_block.as_deref() {
+                Some(block) if !self.block_actions(block).is_empty() => block.to_string(),
+                _ => self.power_block(),
+            }
+        };
+
+        if new_block != self.current_block {
+            let old_block = self.current_block.clone();
+            self.current_block = new_block;
+            log_message(&format!(
+                "Switched active block: {} -> {}",
+                old_block, self.current_block
+            ));
+            
+            // Reset state when switching blocks
+            self.action_index = 0;
+            self.instants_triggered = false;
+            self.notify.notify_one();
+            self.stages_changed.notify_one();
+        }
+    }
+
+    /// Idle time in ms at which each timed action of the active block is
+    /// reached, counted from the last input, as (action index, timeout).
+    /// The debounce delays the first stage, and with it every later one.
+    pub fn stage_timeouts(&self) -> Vec<(usize, u32)> {
+        let mut elapsed: u64 = self.cfg.as_ref().map_or(0, |c| c.debounce_seconds as u64 * 1000);
+        self.get_active_actions()
+            .iter()
+            .enumerate()
+            .filter(|(_, a)| !a.is_instant())
+            .map(|(i, a)| {
+                elapsed = elapsed.saturating_add(a.timeout.saturating_mul(1000));
+                (i, elapsed.min(u32::MAX as u64) as u32)
+            })
+            .collect()
+    }
+
+    /// Block selected by chassis type and power state alone
+    fn power_block(&self) -> String {
+        match &self.chassis {
             ChassisType::Desktop(_) => "default".to_string(),
             ChassisType::Laptop(state) => {
                 if state.on_battery {
-                    if !self.battery_actions.is_empty() {
+                    // Fall back one level at a time when a block isn't configured
+                    if state.level == BatteryLevel::Critical && !self.battery_critical_actions.is_empty() {
+                        "battery_critical".to_string()
+                    } else if state.level != BatteryLevel::Normal && !self.battery_low_actions.is_empty() {
+                        "battery_low".to_string()
+                    } else if !self.battery_actions.is_empty() {
                         "battery".to_string()
                     } else {
                         "default".to_string()
@@ -188,28 +366,27 @@ impl ManagerState {
                     }
                 }
             }
-        };
+        }
+    }
 
-        if new_block != self.current_block {
-            let old_block = self.current_block.clone();
-            self.current_block = new_block;
-            log_message(&format!(
-                "Switched active block: {} -> {}",
-                old_block, self.current_block
-            ));
-            
-            // Reset state when switching blocks
-            self.action_index = 0;
-            self.instants_triggered = false;
-            self.notify.notify_one();
+    fn block_actions(&self, block: &str) -> &[IdleActionBlock] {
+        match block {
+            "ac" => &self.ac_actions,
+            "battery" => &self.battery_actions,
+            "battery_low" => &self.battery_low_actions,
+            "battery_critical" => &self.battery_critical_actions,
+            _ => &self.default_actions,
         }
     }
 
     /// Get the currently active action list based on current_block
     pub fn get_active_actions(&self) -> &[IdleActionBlock] {
         match self.current_block.as_str() {
+            b if b.starts_with("profile:") => &self.profile_actions,
             "ac" => &self.ac_actions,
             "battery" => &self.battery_actions,
+            "battery_low" => &self.battery_low_actions,
+            "battery_critical" => &self.battery_critical_actions,
             "default" => &self.default_actions,
             _ => &self.default_actions,
         }
@@ -218,8 +395,11 @@ impl ManagerState {
     /// Get mutable reference to the currently active action list
     pub fn get_active_actions_mut(&mut self) -> &mut Vec<IdleActionBlock> {
         match self.current_block.as_str() {
+            b if b.starts_with("profile:") => &mut self.profile_actions,
             "ac" => &mut self.ac_actions,
             "battery" => &mut self.battery_actions,
+            "battery_low" => &mut self.battery_low_actions,
+            "battery_critical" => &mut self.battery_critical_actions,
             "default" => &mut self.default_actions,
             _ => &mut self.default_actions,
         }
@@ -236,45 +416,57 @@ impl ManagerState {
 
     pub async fn update_from_config(&mut self, cfg: &StasisConfig) {
         self.active_flags = ActiveFlags::default();
-        self.previous_brightness = None;
+        self.saved_brightness.clear();
         self.pre_suspend_command = cfg.pre_suspend_command.clone();
 
         // Split actions into blocks
-        let default_actions: Vec<_> = cfg
-            .actions
-            .iter()
-            .filter(|a| !a.name.starts_with("ac.") && !a.name.starts_with("battery."))
-            .cloned()
-            .collect();
-
-        let ac_actions: Vec<_> = cfg
-            .actions
-            .iter()
-            .filter(|a| a.name.starts_with("ac."))
-            .cloned()
-            .collect();
-
-        let battery_actions: Vec<_> = cfg
-            .actions
-            .iter()
-            .filter(|a| a.name.starts_with("battery."))
-            .cloned()
-            .collect();
+        let [default_actions, ac_actions, battery_actions, battery_low_actions, battery_critical_actions] =
+            split_actions(cfg);
 
         // Replace the old state vectors
         self.default_actions = default_actions;
         self.ac_actions = ac_actions;
         self.battery_actions = battery_actions;
+        self.battery_low_actions = battery_low_actions;
+        self.battery_critical_actions = battery_critical_actions;
 
         // Reset last_triggered for all actions
-        for actions in [&mut self.default_actions, &mut self.ac_actions, &mut self.battery_actions] {
+        for actions in [
+            &mut self.default_actions,
+            &mut self.ac_actions,
+            &mut self.battery_actions,
+            &mut self.battery_low_actions,
+            &mut self.battery_critical_actions,
+        ] {
             for a in actions.iter_mut() {
                 a.last_triggered = None;
+                a.skipped = false;
             }
         }
 
+        self.cfg = Some(Arc::new(cfg.clone()));
+
+        // Keep the active profile if it still exists, picking up its new actions
+        if let Some(name) = self.active_profile.take() {
+            if cfg.profiles.iter().any(|p| p.name == name) {
+                self.active_profile = Some(name);
+            } else {
+                log_message(&format!("Profile '{}' no longer exists, returning to default actions", name));
+            }
+        }
+        // The schedule is re-evaluated against the new rules right after
+        if !cfg.profiles.iter().any(|p| Some(&p.name) == self.scheduled_profile.as_ref()) {
+            self.scheduled_profile = None;
+        }
+        self.profile_actions = self
+            .active_profile()
+            .map(|p| p.actions.clone())
+            .unwrap_or_default();
+
         // Update current_block based on new config
         self.update_current_block();
+        // Timeouts may have changed even if the block didn't
+        self.stages_changed.notify_one();
 
         // Reset instant trigger flag
         self.instants_triggered = false;
@@ -283,7 +475,6 @@ impl ManagerState {
         let debounce = Duration::from_secs(cfg.debounce_seconds as u64);
         self.debounce = Some(Instant::now() + debounce);
 
-        self.cfg = Some(Arc::new(cfg.clone()));
         self.lock_state = LockState::from_config(cfg);
         self.last_activity = Instant::now();
 
@@ -300,6 +491,73 @@ impl ManagerState {
     }
 }
 
+/// Registry source of `stasis pause` and toggle-inhibit; inhibits from
+/// `stasis inhibit` are held apart from it as `ipc.<reason>`
+pub const MANUAL_INHIBITOR: &str = "ipc";
+
+/// Name of a `stasis inhibit` given without `--reason`, reserved so no
+/// named inhibit can take its place
+pub const UNNAMED_INHIBIT: &str = "inhibit";
+
+pub fn manual_inhibitor_source(reason: Option<&str>) -> String {
+    format!("{}.{}", MANUAL_INHIBITOR, reason.unwrap_or(UNNAMED_INHIBIT))
+}
+
+/// Held by `stasis inhibit`, named or not
+pub fn is_inhibit_command(source: &str) -> bool {
+    source.strip_prefix(MANUAL_INHIBITOR).is_some_and(|rest| rest.starts_with('.'))
+}
+
+/// One entry of the inhibitor registry
+#[derive(Debug, Clone)]
+pub struct Inhibitor {
+    pub scope: InhibitScope,
+    /// What is inhibiting, e.g. the app or the logind lock holder
+    pub reason: String,
+    pub since: SystemTime,
+    /// Wall-clock time at which a timed inhibit lapses, so suspend counts
+    pub expires: Option<SystemTime>,
+}
+
+impl Inhibitor {
+    pub fn new(scope: InhibitScope, reason: impl Into<String>) -> Self {
+        Self { scope, reason: reason.into(), since: SystemTime::now(), expires: None }
+    }
+
+    pub fn expiring_after(mut self, duration: Duration) -> Self {
+        self.expires = Some(SystemTime::now() + duration);
+        self
+    }
+
+    /// Time left on a timed inhibit
+    pub fn remaining(&self) -> Option<Duration> {
+        self.expires.map(|at| at.duration_since(SystemTime::now()).unwrap_or_default())
+    }
+
+    /// Blocks every action, pausing the idle timers
+    pub fn all(reason: impl Into<String>) -> Self {
+        Self::new(InhibitScope::All, reason)
+    }
+}
+
+/// A systemd-logind `idle` block lock held by another process
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogindInhibitor {
+    pub who: String,
+    pub why: String,
+    pub pid: u32,
+}
+
+/// Light level captured before dimming; `device` is None when it was
+/// read through brightnessctl
+#[derive(Debug, Clone)]
+pub struct SavedBrightness {
+    /// "backlight" for panels, "leds" for keyboard backlights
+    pub subsystem: &'static str,
+    pub device: Option<String>,
+    pub value: u32,
+}
+
 #[derive(Debug)]
 pub enum ChassisType {
     Laptop(LaptopState),
@@ -309,6 +567,50 @@ pub enum ChassisType {
 #[derive(Debug)]
 pub struct LaptopState {
     pub on_battery: bool,
+    /// Combined charge of all system batteries, in percent
+    pub capacity: Option<u8>,
+    pub level: BatteryLevel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BatteryLevel {
+    Normal,
+    Low,
+    Critical,
+}
+
+impl std::fmt::Display for BatteryLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BatteryLevel::Normal => write!(f, "normal"),
+            BatteryLevel::Low => write!(f, "low"),
+            BatteryLevel::Critical => write!(f, "critical"),
+        }
+    }
+}
+
+/// Split the configured actions into the default, ac, battery, battery_low
+/// and battery_critical blocks by their name prefix
+fn split_actions(cfg: &StasisConfig) -> [Vec<IdleActionBlock>; 5] {
+    const PREFIXES: [&str; 4] = ["ac.", "battery.", "battery_low.", "battery_critical."];
+
+    let with_prefix = |prefix: &str| -> Vec<IdleActionBlock> {
+        cfg.actions.iter().filter(|a| a.name.starts_with(prefix)).cloned().collect()
+    };
+    let default_actions = cfg
+        .actions
+        .iter()
+        .filter(|a| !PREFIXES.iter().any(|p| a.name.starts_with(p)))
+        .cloned()
+        .collect();
+
+    [
+        default_actions,
+        with_prefix(PREFIXES[0]),
+        with_prefix(PREFIXES[1]),
+        with_prefix(PREFIXES[2]),
+        with_prefix(PREFIXES[3]),
+    ]
 }
 
 #[derive(Debug)]
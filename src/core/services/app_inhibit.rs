@@ -1,19 +1,37 @@
-use std::{collections::HashSet, sync::Arc};
+use std::{collections::{BTreeMap, HashSet}, sync::Arc};
 use tokio::sync::Mutex;
 use tokio::process::Command;
 use serde_json::Value;
 use procfs::process::all_processes;
 
-use crate::config::model::StasisConfig;
-use crate::core::manager::helpers::{decr_active_inhibitor, incr_active_inhibitor};
-use crate::log::log_message;
+use crate::config::model::{AppInhibitPattern, AppInhibitRule, FullscreenInhibit, StasisConfig};
+use crate::core::manager::{helpers::set_inhibitor, state::Inhibitor};
+use crate::core::services::window_tracker::{
+    hyprland_client, hyprland_monitors, niri_window, niri_workspaces, spawn_window_tracker, WindowInfo, WindowTracker,
+};
+use crate::log::{log_error_message, log_message};
 use crate::core::manager::Manager;
 
+/// How often windows are re-checked when no event stream wakes us earlier
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(4);
+
 /// Tracks currently running apps to inhibit idle
 pub struct AppInhibitor {
-    cfg: Arc<StasisConfig>,
+    patterns: Vec<AppInhibitPattern>,
     active_apps: HashSet<String>,
+    rules: Vec<AppInhibitRule>,
+    /// Matching rules as inhibitors, by rule name
+    matched_rules: BTreeMap<String, Inhibitor>,
+    fullscreen_mode: FullscreenInhibit,
+    /// Windows from the last check, None if the compositor couldn't be asked
+    windows: Option<Vec<WindowInfo>>,
+    /// The fullscreen window currently inhibiting, for logging changes
+    fullscreen_app: Option<String>,
     desktop: String,
+    /// Window events from the compositor's IPC, when it offers them
+    tracker: Option<WindowTracker>,
+    /// Rules already warned about matching on data the backend lacks
+    warned_rules: HashSet<String>,
     manager: Arc<Mutex<Manager>>,
 }
 
@@ -26,23 +44,44 @@ impl AppInhibitor {
         log_message(&format!("XDG_CURRENT_DESKTOP detected: {}", desktop));
 
         Self {
-            cfg,
+            patterns: cfg.inhibit_apps.clone(),
             active_apps: HashSet::new(),
+            rules: cfg.inhibit_rules.clone(),
+            matched_rules: BTreeMap::new(),
+            fullscreen_mode: cfg.inhibit_on_fullscreen,
+            windows: None,
+            fullscreen_app: None,
             desktop,
+            tracker: None,
+            warned_rules: HashSet::new(),
             manager,
         }
     }
 
-    /// Returns true if any app in inhibit_apps is currently running
+    /// Returns true if any app in inhibit_apps is currently running, or any
+    /// inhibit rule matches
     pub async fn is_any_app_running(&mut self) -> bool {
+        // Patterns follow the active profile
+        {
+            let mgr = self.manager.lock().await;
+            self.patterns = mgr.state.inhibit_apps();
+            self.fullscreen_mode = mgr.state.cfg.as_ref().map(|c| c.inhibit_on_fullscreen).unwrap_or_default();
+            self.rules = mgr.state.cfg.as_ref().map(|c| c.inhibit_rules.clone()).unwrap_or_default();
+        }
+        self.warn_unmatchable_rules();
+
         let mut new_active_apps = HashSet::new();
 
-        let running = match self.check_compositor_windows().await {
-            Ok(result_apps) => {
-                new_active_apps = result_apps;
+        self.windows = self.current_windows().await.ok();
+        let running = match &self.windows {
+            Some(windows) => {
+                new_active_apps = windows.iter()
+                    .map(|win| win.app_id.clone())
+                    .filter(|app| self.should_inhibit_for_app(app))
+                    .collect();
                 !new_active_apps.is_empty()
             },
-            Err(_) => self.check_processes_with_tracking(&mut new_active_apps),
+            None => self.check_processes_with_tracking(&mut new_active_apps),
         };
 
         for app in &new_active_apps {
@@ -52,7 +91,107 @@ impl AppInhibitor {
         }
 
         self.active_apps = new_active_apps;
-        running
+
+        self.matched_rules = self.rules.iter()
+            .filter(|rule| self.rule_matches(rule))
+            .map(|rule| (rule.name.clone(), Inhibitor::new(rule.scope.clone(), rule.matchers())))
+            .collect();
+        running || !self.matched_rules.is_empty()
+    }
+
+    /// Log once per rule that matches on a workspace the window backend
+    /// can't report, since such a rule would otherwise never match silently
+    fn warn_unmatchable_rules(&mut self) {
+        let Some(tracker) = &self.tracker else { return };
+        if tracker.reports_workspaces() {
+            return;
+        }
+        for rule in self.rules.iter().filter(|r| r.workspace.is_some()) {
+            if self.warned_rules.insert(rule.name.clone()) {
+                log_error_message(&format!(
+                    "Inhibit rule '{}' matches on workspace, which {} doesn't report; it will never match",
+                    rule.name, tracker.backend_name()
+                ));
+            }
+        }
+    }
+
+    /// What inhibit_apps matched on the last check, sorted
+    pub fn running_apps(&self) -> Vec<String> {
+        let mut apps: Vec<String> = self.active_apps.iter().cloned().collect();
+        apps.sort();
+        apps
+    }
+
+    /// Whether a rule matches a window from the last check, or a process
+    /// when it only looks at command lines or there are no windows to go by
+    fn rule_matches(&self, rule: &AppInhibitRule) -> bool {
+        if let Some(windows) = &self.windows
+            && rule.needs_window()
+        {
+            return windows.iter().any(|w| self.rule_matches_window(rule, w));
+        }
+
+        // Without window data only app_id can stand in for the process name
+        if rule.title.is_some() || rule.workspace.is_some() || rule.output.is_some() {
+            return false;
+        }
+
+        let Ok(processes) = all_processes() else {
+            return false;
+        };
+        processes.flatten().any(|process| {
+            let app_matches = rule.app_id.as_ref().is_none_or(|pattern| {
+                std::fs::read_to_string(format!("/proc/{}/comm", process.pid))
+                    .is_ok_and(|name| pattern.matches(name.trim()))
+            });
+            app_matches && rule.cmdline.as_ref().is_none_or(|pattern| {
+                read_cmdline(process.pid).is_some_and(|cmdline| pattern.is_found_in(&cmdline))
+            })
+        })
+    }
+
+    fn rule_matches_window(&self, rule: &AppInhibitRule, window: &WindowInfo) -> bool {
+        let optional = |pattern: &Option<AppInhibitPattern>, value: &Option<String>| {
+            pattern.as_ref().is_none_or(|p| value.as_deref().is_some_and(|v| p.matches(v)))
+        };
+
+        rule.app_id.as_ref().is_none_or(|p| self.pattern_matches_app(p, &window.app_id))
+            && rule.title.as_ref().is_none_or(|p| p.is_found_in(&window.title))
+            && optional(&rule.workspace, &window.workspace)
+            && optional(&rule.output, &window.output)
+            && rule.cmdline.as_ref().is_none_or(|p| {
+                window.pid
+                    .and_then(|pid| read_cmdline(pid as i32))
+                    .is_some_and(|cmdline| p.is_found_in(&cmdline))
+            })
+    }
+
+    /// Matching rules from the last check, as the inhibitors they hold
+    pub fn matched_rules(&self) -> &BTreeMap<String, Inhibitor> {
+        &self.matched_rules
+    }
+
+    /// The app_id of a fullscreen window that should inhibit, judged on the
+    /// windows from the last `is_any_app_running`
+    pub fn fullscreen_window(&self) -> Option<String> {
+        if self.fullscreen_mode == FullscreenInhibit::Off {
+            return None;
+        }
+        let windows = self.windows.as_ref()?;
+        let focused_output = windows.iter().find(|w| w.focused).and_then(|w| w.output.as_ref());
+
+        windows.iter()
+            .filter(|w| w.fullscreen)
+            .find(|w| match self.fullscreen_mode {
+                FullscreenInhibit::Off => false,
+                FullscreenInhibit::Any => true,
+                FullscreenInhibit::FocusedOutput => {
+                    w.focused || w.output.as_ref().is_some_and(|o| Some(o) == focused_output)
+                }
+                FullscreenInhibit::Apps => self.should_inhibit_for_app(&w.app_id),
+            })
+            .map(|w| w.app_id.clone())
     }
 
     /// Process-based fallback - only refresh what we need
@@ -78,12 +217,12 @@ impl AppInhibitor {
             };
 
             // Compare against inhibit patterns
-            for pattern in &self.cfg.inhibit_apps {
+            for pattern in &self.patterns {
                 let matched = match pattern {
-                    crate::config::model::AppInhibitPattern::Literal(s) => {
+                    AppInhibitPattern::Literal(s) => {
                         proc_name.eq_ignore_ascii_case(s)
                     }
-                    crate::config::model::AppInhibitPattern::Regex(r) => r.is_match(&proc_name),
+                    AppInhibitPattern::Regex(r) => r.is_match(&proc_name),
                 };
 
                 if matched {
@@ -97,64 +236,64 @@ impl AppInhibitor {
         any_running
     }    
     
-    /// Check compositor windows via IPC
-    async fn check_compositor_windows(&self) -> Result<HashSet<String>, Box<dyn std::error::Error + Send + Sync>> {
+    /// Compositor windows, from the event stream or by polling its IPC
+    async fn current_windows(&self) -> Result<Vec<WindowInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        if let Some(tracker) = &self.tracker
+            && let Some(windows) = tracker.windows().await
+        {
+            return Ok(windows);
+        }
+
+        // Polling fallback while no event stream is available
         match self.desktop.as_str() {
-            "niri" => {
-                let app_ids = self.try_niri_ipc().await?;
-                Ok(app_ids.into_iter()
-                    .filter(|app| self.should_inhibit_for_app(app))
-                    .collect())
-            }
-            "hyprland" => {
-                let windows = self.try_hyprland_ipc().await?;
-                Ok(windows.into_iter()
-                    .filter_map(|win| win.get("app_id").and_then(|v| v.as_str()).map(|s| s.to_string()))
-                    .filter(|app| self.should_inhibit_for_app(app))
-                    .collect())
-            }
+            "niri" => self.try_niri_ipc().await,
+            "hyprland" => self.try_hyprland_ipc().await,
             _ => Err("No IPC available, fallback to process scan".into())
         }
     }
 
-    async fn try_niri_ipc(&self) -> Result<Vec<String>, Box<dyn std::error::Error + Send + Sync>> {
-        let output = Command::new("niri").args(&["msg", "windows"]).output().await?;
+    async fn try_niri_ipc(&self) -> Result<Vec<WindowInfo>, Box<dyn std::error::Error + Send + Sync>> {
+        let output = Command::new("niri").args(["msg", "--json", "windows"]).output().await?;
         if !output.status.success() {
             return Err(format!("niri command failed: {}", String::from_utf8_lossy(&output.stderr)).into());
         }
-        let text = String::from_utf8(output.stdout)?;
-        Ok(text.lines()
-            .filter_map(|line| line.strip_prefix("  App ID: "))
-            .map(|s| s.trim_matches('"').to_string())
-            .collect())
+        let windows: Vec<Value> = serde_json::from_slice(&output.stdout)?;
+
+        // Workspace names are only needed for rules, don't fail without them
+        let workspaces = Command::new("niri").args(["msg", "--json", "workspaces"]).output().await.ok()
+            .filter(|out| out.status.success())
+            .and_then(|out| serde_json::from_slice::<Vec<Value>>(&out.stdout).ok())
+            .map(|workspaces| niri_workspaces(&workspaces))
+            .unwrap_or_default();
+        Ok(windows.iter().filter_map(|w| niri_window(w, &workspaces)).map(|(_, win)| win).collect())
     }
 
-    async fn try_hyprland_ipc(&self) -> Result<Vec<Value>, Box<dyn std::error::Error + Send + Sync>> {
+    async fn try_hyprland_ipc(&self) -> Result<Vec<WindowInfo>, Box<dyn std::error::Error + Send + Sync>> {
         let output = Command::new("hyprctl").args(&["clients", "-j"]).output().await?;
         if !output.status.success() {
             return Err(format!("hyprctl command failed: {}", String::from_utf8_lossy(&output.stderr)).into());
         }
 
         let clients: Vec<Value> = serde_json::from_slice(&output.stdout)?;
-        let windows = clients.into_iter().map(|mut client| {
-            if let Some(class) = client.get("class").cloned() {
-                client.as_object_mut().unwrap().insert("app_id".to_string(), class);
-            }
-            client
-        }).collect();
 
-        Ok(windows)
+        // Monitor names are only needed for rules, fall back to ids without them
+        let monitors = Command::new("hyprctl").args(["monitors", "-j"]).output().await.ok()
+            .filter(|out| out.status.success())
+            .and_then(|out| serde_json::from_slice::<Value>(&out.stdout).ok())
+            .map(|monitors| hyprland_monitors(&monitors))
+            .unwrap_or_default();
+        Ok(clients.iter().filter_map(|c| hyprland_client(c, &monitors)).map(|(_, win)| win).collect())
     }
 
     fn should_inhibit_for_app(&self, app_id: &str) -> bool {
-        for pattern in &self.cfg.inhibit_apps {
-            let matched = match pattern {
-                crate::config::model::AppInhibitPattern::Literal(s) => self.app_id_matches(s, app_id),
-                crate::config::model::AppInhibitPattern::Regex(r) => r.is_match(app_id),
-            };
-            if matched { return true; }
+        self.patterns.iter().any(|pattern| self.pattern_matches_app(pattern, app_id))
+    }
+
+    fn pattern_matches_app(&self, pattern: &AppInhibitPattern, app_id: &str) -> bool {
+        match pattern {
+            AppInhibitPattern::Literal(s) => self.app_id_matches(s, app_id),
+            AppInhibitPattern::Regex(r) => r.is_match(app_id),
         }
-        false
     }
 
     fn app_id_matches(&self, pattern: &str, app_id: &str) -> bool {
@@ -176,14 +315,24 @@ impl AppInhibitor {
     }
 }
 
+/// A process's command line with arguments separated by spaces
+fn read_cmdline(pid: i32) -> Option<String> {
+    let raw = std::fs::read(format!("/proc/{}/cmdline", pid)).ok()?;
+    let cmdline = String::from_utf8_lossy(&raw).replace('\0', " ");
+    let cmdline = cmdline.trim();
+    (!cmdline.is_empty()).then(|| cmdline.to_string())
+}
+
 pub async fn spawn_app_inhibit_task(
     manager: Arc<Mutex<Manager>>,
     cfg: Arc<StasisConfig>,
 ) -> Arc<Mutex<AppInhibitor>> {
     let inhibitor = Arc::new(Mutex::new(AppInhibitor::new(cfg.clone(), Arc::clone(&manager))));
 
-    // If no inhibit apps are configured, sleep forever
-    if cfg.inhibit_apps.is_empty() {
+    // If no inhibit apps are configured (globally or in any profile), sleep forever
+    let profile_apps = cfg.profiles.iter().any(|p| p.inhibit_apps.as_ref().is_some_and(|a| !a.is_empty()));
+    let fullscreen = cfg.inhibit_on_fullscreen != FullscreenInhibit::Off;
+    if cfg.inhibit_apps.is_empty() && !profile_apps && !fullscreen && cfg.inhibit_rules.is_empty() {
         log_message("No inhibit_apps configured, sleeping app inhibitor.");
         tokio::spawn(async move {
             futures::future::pending::<()>().await;
@@ -191,32 +340,63 @@ pub async fn spawn_app_inhibit_task(
         return inhibitor;
     }
 
+    // Window events wake the loop right away; polling stays as the fallback
+    let changed = {
+        let mut guard = inhibitor.lock().await;
+        let tracker = spawn_window_tracker(&guard.desktop, fullscreen);
+        let changed = Arc::clone(&tracker.changed);
+        guard.tracker = Some(tracker);
+        changed
+    };
+
     let inhibitor_clone = Arc::clone(&inhibitor);
 
     tokio::spawn(async move {
-        let mut inhibitor_active = false; // track previous inhibitor state locally
+        let mut rules_active: BTreeMap<String, Inhibitor> = BTreeMap::new();
 
         loop {
-            let running = {
+            let (apps, fullscreen, rules) = {
                 let mut guard = inhibitor_clone.lock().await;
-                guard.is_any_app_running().await
+                guard.is_any_app_running().await;
+                let fullscreen = guard.fullscreen_window();
+                if fullscreen != guard.fullscreen_app {
+                    match &fullscreen {
+                        Some(app) if app.is_empty() => log_message("Fullscreen inhibit active: window without app_id"),
+                        Some(app) => log_message(&format!("Fullscreen inhibit active: {}", app)),
+                        None => log_message("Fullscreen inhibit released"),
+                    }
+                    guard.fullscreen_app = fullscreen.clone();
+                }
+                (guard.running_apps(), fullscreen, guard.matched_rules().clone())
             };
 
-            if running && !inhibitor_active {
-                // App started inhibiting
-                let guard = inhibitor_clone.lock().await;
-                let mut mgr = guard.manager.lock().await;
-                incr_active_inhibitor(&mut mgr).await;
-                inhibitor_active = true;
-            } else if !running && inhibitor_active {
-                // All apps stopped inhibiting
+            // Apps, fullscreen windows and each rule are separate inhibitors;
+            // unchanged ones are left alone, changed scopes (after a reload) swapped
+            {
                 let guard = inhibitor_clone.lock().await;
                 let mut mgr = guard.manager.lock().await;
-                decr_active_inhibitor(&mut mgr).await;
-                inhibitor_active = false;
+                let scopes = mgr.state.cfg.as_ref().map(|c| c.inhibit_scopes.clone()).unwrap_or_default();
+                let apps = (!apps.is_empty()).then(|| Inhibitor::new(scopes.apps, apps.join(", ")));
+                set_inhibitor(&mut mgr, "apps", apps).await;
+                let fullscreen = fullscreen.map(|app| {
+                    let reason = if app.is_empty() { "window without app_id".to_string() } else { app };
+                    Inhibitor::new(scopes.fullscreen, reason)
+                });
+                set_inhibitor(&mut mgr, "fullscreen", fullscreen).await;
+
+                for name in rules_active.keys().filter(|name| !rules.contains_key(*name)) {
+                    set_inhibitor(&mut mgr, &format!("inhibit_rules.{}", name), None).await;
+                }
+                for (name, inhibitor) in &rules {
+                    set_inhibitor(&mut mgr, &format!("inhibit_rules.{}", name), Some(inhibitor.clone())).await;
+                }
+                rules_active = rules;
             }
 
-            tokio::time::sleep(std::time::Duration::from_secs(4)).await;
+            tokio::select! {
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                _ = changed.notified() => {}
+            }
         }
     });
 
@@ -0,0 +1,243 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::{CString, OsStr},
+    os::unix::{
+        ffi::OsStrExt,
+        io::{AsRawFd, FromRawFd, OwnedFd},
+    },
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+use tokio::{io::unix::AsyncFd, sync::Mutex, time::sleep};
+
+use crate::{
+    config::{config_search_paths, ConfigSource},
+    core::manager::Manager,
+    ipc::commands::reload_config,
+    log::{log_error_message, log_message},
+};
+
+/// Editors usually emit several events per save; wait for them to settle
+const SETTLE_DELAY: Duration = Duration::from_millis(300);
+
+/// Size of the fixed part of `struct inotify_event`
+const EVENT_HEADER_LEN: usize = std::mem::size_of::<libc::inotify_event>();
+
+/// Watches every config layer and reloads through the same path as `stasis reload`.
+///
+/// Parent directories are watched rather than the files themselves so that
+/// editors which save by renaming, and newly created layers, are picked up.
+/// Symlinked configs (e.g. from a dotfiles repo) also watch the link target,
+/// and a config dir that doesn't exist yet is waited for from its nearest
+/// existing ancestor. The watch set is resolved again after every change.
+pub async fn spawn_config_watcher(manager: Arc<Mutex<Manager>>) {
+    let (source, shutdown) = {
+        let mgr = manager.lock().await;
+        (mgr.state.config_source.clone(), mgr.state.shutdown_flag.clone())
+    };
+
+    let raw_fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+    if raw_fd < 0 {
+        log_error_message(&format!(
+            "Config watcher disabled, inotify_init1 failed: {}",
+            std::io::Error::last_os_error()
+        ));
+        return;
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(raw_fd) };
+
+    let mut watch = WatchSet::default();
+    watch.refresh(&fd, &source);
+    if watch.watches.is_empty() {
+        log_message("No config directories to watch, hot-reload disabled");
+        return;
+    }
+
+    // SAFETY: the OwnedFd moves into the AsyncFd, so it stays open for as long as it's registered
+    let async_fd = match unsafe { AsyncFd::register(fd) } {
+        Ok(fd) => fd,
+        Err(e) => {
+            log_error_message(&format!("Config watcher disabled: {}", e));
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            tokio::select! {
+                ready = async_fd.readable() => {
+                    let mut guard = match ready {
+                        Ok(guard) => guard,
+                        Err(e) => {
+                            log_error_message(&format!("Config watcher error: {}", e));
+                            break;
+                        }
+                    };
+
+                    let mut events = watch.read_events(async_fd.get_ref());
+                    guard.clear_ready();
+                    if events.changed.is_none() && !events.rewatch {
+                        continue;
+                    }
+
+                    sleep(SETTLE_DELAY).await;
+                    // Take in the rest of the burst from the same save
+                    events.merge(watch.read_events(async_fd.get_ref()));
+
+                    // A config dir may have appeared, or a symlink been pointed elsewhere
+                    let appeared = watch.refresh(async_fd.get_ref(), &source);
+                    if let Some(path) = events.changed.or(appeared) {
+                        log_message(&format!("Config file changed ({}), reloading...", path.display()));
+                        let _ = reload_config(&manager).await;
+                    }
+                }
+                _ = shutdown.notified() => break,
+            }
+        }
+
+        log_message("Config watcher shutting down...");
+    });
+}
+
+/// What a batch of inotify events means for the config
+#[derive(Default)]
+struct WatchEvents {
+    /// A config file that changed
+    changed: Option<PathBuf>,
+    /// Something on the way to a config file changed, or a watch went away
+    rewatch: bool,
+}
+
+impl WatchEvents {
+    fn merge(&mut self, other: WatchEvents) {
+        self.changed = self.changed.take().or(other.changed);
+        self.rewatch |= other.rewatch;
+    }
+}
+
+/// Config files and the directories watched for them
+#[derive(Default)]
+struct WatchSet {
+    files: HashSet<PathBuf>,
+    /// Directories that lead to a config file without holding one: the
+    /// nearest existing ancestor of a missing dir, or the parent of a symlink
+    paths: HashSet<PathBuf>,
+    watches: HashMap<i32, PathBuf>,
+}
+
+impl WatchSet {
+    /// Resolve the config files again and bring the watches in line.
+    /// Returns a config file found in a directory that wasn't watched before,
+    /// as it may have been written before its directory's watch was in place.
+    fn refresh(&mut self, fd: &OwnedFd, source: &ConfigSource) -> Option<PathBuf> {
+        let mut files: HashSet<PathBuf> = HashSet::new();
+        let mut paths: HashSet<PathBuf> = HashSet::new();
+        for path in config_search_paths(source) {
+            if let Ok(target) = std::fs::canonicalize(&path) {
+                files.insert(target);
+            }
+            // Symlinked dirs along the way, e.g. ~/.config/stasis -> ~/dotfiles/stasis
+            for ancestor in path.ancestors().skip(1) {
+                if ancestor.is_symlink() {
+                    paths.extend(ancestor.parent().and_then(|p| std::fs::canonicalize(p).ok()));
+                }
+            }
+            files.insert(path);
+        }
+
+        // Watched by their real path, as inotify hands out one watch per inode
+        let mut dirs: HashSet<PathBuf> = HashSet::new();
+        for dir in files.iter().filter_map(|f| f.parent()) {
+            let Some(existing) = dir.ancestors().find(|a| a.is_dir()) else { continue };
+            let Ok(real) = std::fs::canonicalize(existing) else { continue };
+            if existing == dir {
+                dirs.insert(real);
+            } else {
+                paths.insert(real);
+            }
+        }
+        dirs.extend(paths.iter().cloned());
+
+        let stale: Vec<i32> = self.watches.iter()
+            .filter(|(_, dir)| !dirs.contains(*dir))
+            .map(|(wd, _)| *wd)
+            .collect();
+        for wd in stale {
+            unsafe { libc::inotify_rm_watch(fd.as_raw_fd(), wd) };
+            self.watches.remove(&wd);
+        }
+
+        let mut appeared = None;
+        for dir in dirs {
+            if self.watches.values().any(|watched| *watched == dir) {
+                continue;
+            }
+            let Ok(c_dir) = CString::new(dir.as_os_str().as_bytes()) else { continue };
+            let mask = libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO | libc::IN_CREATE | libc::IN_DELETE | libc::IN_MOVED_FROM;
+            let wd = unsafe { libc::inotify_add_watch(fd.as_raw_fd(), c_dir.as_ptr(), mask) };
+            if wd < 0 {
+                log_error_message(&format!(
+                    "Failed to watch {}: {}",
+                    dir.display(),
+                    std::io::Error::last_os_error()
+                ));
+                continue;
+            }
+            log_message(&format!("Watching {} for config changes", dir.display()));
+            if appeared.is_none() && !paths.contains(&dir) {
+                appeared = files.iter().find(|f| f.parent() == Some(dir.as_path()) && f.is_file()).cloned();
+            }
+            self.watches.insert(wd, dir);
+        }
+
+        self.files = files;
+        self.paths = paths;
+        appeared
+    }
+
+    /// Drain pending inotify events
+    fn read_events(&mut self, fd: &OwnedFd) -> WatchEvents {
+        let mut events = WatchEvents::default();
+        let mut buf = [0u8; 4096];
+
+        loop {
+            let n = unsafe { libc::read(fd.as_raw_fd(), buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+            if n <= 0 {
+                break;
+            }
+
+            let mut offset = 0;
+            while offset + EVENT_HEADER_LEN <= n as usize {
+                let event = unsafe { std::ptr::read_unaligned(buf.as_ptr().add(offset) as *const libc::inotify_event) };
+                let name_start = offset + EVENT_HEADER_LEN;
+                let name_end = name_start + event.len as usize;
+                offset = name_end;
+
+                // The directory itself went away
+                if event.mask & libc::IN_IGNORED != 0 {
+                    self.watches.remove(&event.wd);
+                    events.rewatch = true;
+                    continue;
+                }
+
+                let name: Vec<u8> = buf[name_start..name_end.min(n as usize)]
+                    .iter()
+                    .copied()
+                    .take_while(|b| *b != 0)
+                    .collect();
+
+                if let Some(dir) = self.watches.get(&event.wd) {
+                    let path = dir.join(OsStr::from_bytes(&name));
+                    if self.files.contains(&path) {
+                        events.changed = Some(path);
+                    } else if self.paths.contains(dir) {
+                        events.rewatch = true;
+                    }
+                }
+            }
+        }
+
+        events
+    }
+}
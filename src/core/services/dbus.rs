@@ -1,14 +1,13 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 use futures::StreamExt;
 use tokio::sync::Mutex;
 use zbus::{Connection, fdo::Result as ZbusResult, Proxy, MatchRule};
 use zvariant::Value;
 use crate::core::events::handlers::{handle_event, Event};
-use crate::core::manager::Manager;
+use crate::core::manager::{helpers::set_logind_inhibitors, state::LogindInhibitor, Manager};
 use crate::log::log_message;
 
-pub async fn listen_for_suspend_events(idle_manager: Arc<Mutex<Manager>>) -> ZbusResult<()> {
-    let connection = Connection::system().await?;
+pub async fn listen_for_suspend_events(idle_manager: Arc<Mutex<Manager>>, connection: Connection) -> ZbusResult<()> {
     let proxy = Proxy::new(
         &connection,
         "org.freedesktop.login1",
@@ -39,8 +38,7 @@ pub async fn listen_for_suspend_events(idle_manager: Arc<Mutex<Manager>>) -> Zbu
     Ok(())
 }
 
-pub async fn listen_for_lid_events(idle_manager: Arc<Mutex<Manager>>) -> ZbusResult<()> {
-    let connection = Connection::system().await?;
+pub async fn listen_for_lid_events(idle_manager: Arc<Mutex<Manager>>, connection: Connection) -> ZbusResult<()> {
     log_message("Listening for D-Bus lid events via UPower...");
     
     // Create a match rule for PropertiesChanged signals from UPower
@@ -100,8 +98,7 @@ pub async fn listen_for_lid_events(idle_manager: Arc<Mutex<Manager>>) -> ZbusRes
     Ok(())
 }
 
-pub async fn listen_for_lock_events(idle_manager: Arc<Mutex<Manager>>) -> ZbusResult<()> {
-    let connection = Connection::system().await?;
+pub async fn listen_for_lock_events(idle_manager: Arc<Mutex<Manager>>, connection: Connection) -> ZbusResult<()> {
     log_message("Listening for D-Bus lock/unlock events...");
     
     // Get the session path for the current session
@@ -144,6 +141,18 @@ pub async fn listen_for_lock_events(idle_manager: Arc<Mutex<Manager>>) -> ZbusRe
     Ok(())
 }
 
+/// Proxy for our own logind session, e.g. to call Session.SetBrightness
+pub async fn session_proxy() -> ZbusResult<Proxy<'static>> {
+    let connection = Connection::system().await?;
+    let path = get_current_session_path(&connection).await?;
+    Ok(Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        path,
+        "org.freedesktop.login1.Session"
+    ).await?)
+}
+
 async fn get_current_session_path(connection: &Connection) -> ZbusResult<zvariant::OwnedObjectPath> {
     let proxy = Proxy::new(
         connection,
@@ -231,30 +240,109 @@ async fn get_current_session_path(connection: &Connection) -> ZbusResult<zvarian
     }
 }
 
-// Combined listener that handles suspend, lid, and lock events
-pub async fn listen_for_power_events(idle_manager: Arc<Mutex<Manager>>) -> ZbusResult<()> {
+// Combined listener that handles suspend, lid, lock and idle inhibitor events,
+// all on the shared system bus connection
+pub async fn listen_for_power_events(idle_manager: Arc<Mutex<Manager>>, connection: Connection) -> ZbusResult<()> {
     let suspend_manager = Arc::clone(&idle_manager);
     let lid_manager = Arc::clone(&idle_manager);
     let lock_manager = Arc::clone(&idle_manager);
+    let inhibitor_manager = Arc::clone(&idle_manager);
+    let suspend_connection = connection.clone();
+    let lid_connection = connection.clone();
+    let lock_connection = connection.clone();
     
     let suspend_handle = tokio::spawn(async move {
-        if let Err(e) = listen_for_suspend_events(suspend_manager).await {
+        if let Err(e) = listen_for_suspend_events(suspend_manager, suspend_connection).await {
             log_message(&format!("Suspend listener error: {e:?}"));
         }
     });
     
     let lid_handle = tokio::spawn(async move {
-        if let Err(e) = listen_for_lid_events(lid_manager).await {
+        if let Err(e) = listen_for_lid_events(lid_manager, lid_connection).await {
             log_message(&format!("Lid listener error: {e:?}"));
         }
     });
     
     let lock_handle = tokio::spawn(async move {
-        if let Err(e) = listen_for_lock_events(lock_manager).await {
+        if let Err(e) = listen_for_lock_events(lock_manager, lock_connection).await {
             log_message(&format!("Lock listener error: {e:?}"));
         }
     });
     
-    let _ = tokio::try_join!(suspend_handle, lid_handle, lock_handle);
+    let inhibitor_handle = tokio::spawn(async move {
+        if let Err(e) = listen_for_logind_inhibitors(inhibitor_manager, connection).await {
+            log_message(&format!("logind inhibitor listener error: {e:?}"));
+        }
+    });
+    
+    let _ = tokio::try_join!(suspend_handle, lid_handle, lock_handle, inhibitor_handle);
     Ok(())
 }
+
+/// How often logind is asked for its current block locks
+const LOGIND_INHIBITOR_POLL: Duration = Duration::from_secs(5);
+
+/// Track logind `idle` block locks (e.g. `systemd-inhibit --what=idle`).
+/// logind doesn't emit PropertiesChanged for BlockInhibited, so
+/// ListInhibitors is polled, starting with the locks held at startup.
+pub async fn listen_for_logind_inhibitors(idle_manager: Arc<Mutex<Manager>>, connection: Connection) -> ZbusResult<()> {
+    let proxy = Proxy::new(
+        &connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager"
+    ).await?;
+
+    let mut ticker = tokio::time::interval(LOGIND_INHIBITOR_POLL);
+    let mut last: Option<Vec<LogindInhibitor>> = None;
+    log_message("Listening for logind idle inhibitors...");
+
+    loop {
+        ticker.tick().await;
+
+        let locks: Vec<(String, String, String, String, u32, u32)> = match proxy.call("ListInhibitors", &()).await {
+            Ok(locks) => locks,
+            Err(e) => {
+                log_message(&format!("Failed to list logind inhibitors: {e:?}"));
+                continue;
+            }
+        };
+
+        let inhibitors: Vec<LogindInhibitor> = locks
+            .into_iter()
+            .filter(|(what, _, _, mode, _, _)| mode == "block" && what.split(':').any(|w| w == "idle"))
+            .map(|(_, who, why, _, _, pid)| LogindInhibitor { who, why, pid })
+            .collect();
+
+        if last.as_ref() == Some(&inhibitors) {
+            continue;
+        }
+        last = Some(inhibitors.clone());
+
+        let mut mgr = idle_manager.lock().await;
+        set_logind_inhibitors(&mut mgr, inhibitors).await;
+    }
+}
+
+/// Put the system to sleep through logind, e.g. `method` = "Hibernate".
+/// Checks the matching `Can*` call first so unsupported or forbidden
+/// sleep states give a clear error instead of a bare D-Bus failure.
+pub async fn logind_sleep(connection: &Connection, method: &str) -> Result<(), String> {
+    let proxy = Proxy::new(
+        connection,
+        "org.freedesktop.login1",
+        "/org/freedesktop/login1",
+        "org.freedesktop.login1.Manager"
+    ).await.map_err(|e| format!("failed to reach logind: {}", e))?;
+
+    let can: String = proxy.call(format!("Can{}", method).as_str(), &()).await
+        .map_err(|e| format!("logind Can{} failed: {}", method, e))?;
+    // "challenge" would need interactive authorization, which we can't answer
+    if can != "yes" {
+        return Err(format!("logind reports {} is not available (Can{}={})", method, method, can));
+    }
+
+    log_message(&format!("Requesting {} via logind", method));
+    proxy.call::<_, _, ()>(method, &(false,)).await
+        .map_err(|e| format!("logind {} failed: {}", method, e))
+}
@@ -5,7 +5,7 @@ use mpris::{PlayerFinder, PlaybackStatus};
 use tokio::task;
 use zbus::{Connection, MatchRule, MessageStream};
 
-use crate::core::manager::{helpers::{decr_active_inhibitor, incr_active_inhibitor}, Manager};
+use crate::core::manager::{helpers::set_media_playing, Manager};
 
 const IGNORED_PLAYERS: &[&str] = &[
     "KDE Connect", "kdeconnect", "Chromecast", "chromecast",
@@ -36,42 +36,35 @@ pub async fn spawn_media_monitor_dbus(manager: Arc<tokio::sync::Mutex<Manager>>)
 
         // Initial check
         {
-            let (ignore_remote_media, media_blacklist) = {
+            let (enabled, ignore_remote_media, media_blacklist) = {
                 let mgr = manager.lock().await;
                 let ignore = mgr.state.cfg.as_ref().map(|c| c.ignore_remote_media).unwrap_or(false);
                 let blacklist = mgr.state.cfg.as_ref().map(|c| c.media_blacklist.clone()).unwrap_or_default();
-                (ignore, blacklist)
+                (mgr.state.monitor_media(), ignore, blacklist)
             };
 
-            let playing = check_media_playing(ignore_remote_media, &media_blacklist);
+            let playing = enabled && check_media_playing(ignore_remote_media, &media_blacklist);
             if playing {
                 // use manager helper to ensure consistent side-effects/logging
                 let mut mgr = manager.lock().await;
-                if !mgr.state.media_playing {
-                    incr_active_inhibitor(&mut mgr).await;
-                    mgr.state.media_playing = true;
-                }
+                set_media_playing(&mut mgr, true).await;
             }
         }
 
         loop {
             if let Some(_msg) = stream.next().await {
-                let (ignore_remote_media, media_blacklist) = {
+                let (enabled, ignore_remote_media, media_blacklist) = {
                     let mgr = manager.lock().await;
                     let ignore = mgr.state.cfg.as_ref().map(|c| c.ignore_remote_media).unwrap_or(false);
                     let blacklist = mgr.state.cfg.as_ref().map(|c| c.media_blacklist.clone()).unwrap_or_default();
-                    (ignore, blacklist)
+                    (mgr.state.monitor_media(), ignore, blacklist)
                 };
 
-                let any_playing = check_media_playing(ignore_remote_media, &media_blacklist);
+                let any_playing = enabled && check_media_playing(ignore_remote_media, &media_blacklist);
 
                 let mut mgr = manager.lock().await;
-                if any_playing && !mgr.state.media_playing {
-                    incr_active_inhibitor(&mut mgr).await;
-                    mgr.state.media_playing = true;
-                } else if !any_playing && mgr.state.media_playing {
-                    decr_active_inhibitor(&mut mgr).await;
-                    mgr.state.media_playing = false;
+                if any_playing != mgr.state.is_inhibited_by("media") {
+                    set_media_playing(&mut mgr, any_playing).await;
                 }
             }
         }
@@ -1,6 +1,10 @@
 pub mod app_inhibit;
+pub mod config_watcher;
 pub mod dbus;
 pub mod input;
 pub mod media;
 pub mod power_detection;
+pub mod schedule;
+pub mod screensaver;
 pub mod wayland;
+pub mod window_tracker;
@@ -1,10 +1,14 @@
 use std::{fs, sync::Arc, time::Duration};
+use futures::StreamExt;
 use tokio::sync::Mutex;
+use zbus::{fdo::DBusProxy, Connection, Proxy};
 
 use crate::core::manager::Manager;
 use crate::log::log_message;
 use crate::core::events::handlers::{handle_event, Event};
 
+const UPOWER_SERVICE: &str = "org.freedesktop.UPower";
+
 /// Detect initial AC/Battery state.
 /// Returns true if on AC power.
 pub async fn detect_initial_power_state(manager: &Arc<Mutex<Manager>>) -> bool {
@@ -16,16 +20,21 @@ pub async fn detect_initial_power_state(manager: &Arc<Mutex<Manager>>) -> bool {
     drop(mgr);
 
     let on_ac = is_on_ac_power().await;
+    let capacity = read_battery_capacity();
 
     {
         let mut mgr = manager.lock().await;
+        if let Some(capacity) = capacity {
+            mgr.state.set_battery_capacity(capacity);
+        }
         mgr.state.set_on_battery(!on_ac);
     }
 
     let current_block = manager.lock().await.state.current_block.clone();
     log_message(&format!(
-        "Initial power detection: {} (active block: {})",
+        "Initial power detection: {}{} (active block: {})",
         if on_ac { "AC" } else { "Battery" },
+        capacity.map(|c| format!(", {}%", c)).unwrap_or_default(),
         current_block
     ));
     on_ac
@@ -62,39 +71,168 @@ async fn is_on_ac_power() -> bool {
     false
 }
 
+/// Combined charge of all system batteries in percent.
+/// Peripheral batteries (mice, headsets) report scope "Device" and are skipped.
+pub fn read_battery_capacity() -> Option<u8> {
+    let read = |path: &std::path::Path, file: &str| -> Option<u64> {
+        fs::read_to_string(path.join(file)).ok()?.trim().parse().ok()
+    };
+
+    let mut now_total = 0u64;
+    let mut full_total = 0u64;
+    let mut capacities = Vec::new();
+
+    for entry in fs::read_dir("/sys/class/power_supply/").ok()?.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        let is_battery = fs::read_to_string(path.join("type")).is_ok_and(|t| t.trim() == "Battery");
+        let is_peripheral = fs::read_to_string(path.join("scope")).is_ok_and(|s| s.trim() == "Device");
+        if !is_battery || is_peripheral {
+            continue;
+        }
+
+        // Weigh multiple batteries by their size when the kernel reports it
+        let energy = read(&path, "energy_now").zip(read(&path, "energy_full"));
+        let charge = read(&path, "charge_now").zip(read(&path, "charge_full"));
+        if let Some((now, full)) = energy.or(charge) {
+            now_total += now;
+            full_total += full;
+        } else if let Some(capacity) = read(&path, "capacity") {
+            capacities.push(capacity);
+        }
+    }
+
+    if full_total > 0 && capacities.is_empty() {
+        Some((now_total * 100 / full_total).min(100) as u8)
+    } else if !capacities.is_empty() {
+        Some((capacities.iter().sum::<u64>() / capacities.len() as u64).min(100) as u8)
+    } else {
+        None
+    }
+}
+
 /// Background monitor for power source changes.
-/// Emits ACConnected / ACDisconnected events through the central event system.
-pub async fn spawn_power_source_monitor(manager: Arc<Mutex<Manager>>) {
-    let mgr = manager.lock().await;
-    let last_on_ac = !mgr.state.on_battery().unwrap_or(false);
-    drop(mgr);
+/// Emits ACConnected / ACDisconnected and BatteryCapacity events through the central event system.
+/// Subscribes to UPower on the shared system bus connection when it is available and falls back
+/// to polling sysfs otherwise, switching back once UPower returns to the bus.
+pub async fn spawn_power_source_monitor(manager: Arc<Mutex<Manager>>, system_bus: Option<Connection>) {
+    if !manager.lock().await.state.is_laptop() {
+        return;
+    }
 
-    let mut last_on_ac = last_on_ac;
-    let mut ticker = tokio::time::interval(Duration::from_secs(5));
+    let Some(connection) = system_bus else {
+        log_message("No system bus, polling sysfs for power source changes");
+        poll_power_source(&manager, None).await;
+        return;
+    };
 
     loop {
-        ticker.tick().await;
+        match watch_upower(&manager, &connection).await {
+            Ok(()) => log_message("UPower went away, falling back to sysfs power polling"),
+            Err(e) => log_message(&format!("UPower unavailable ({}), falling back to sysfs power polling", e)),
+        }
 
-        let mgr = manager.lock().await;
-        if !mgr.state.is_laptop() {
-            continue;
+        poll_power_source(&manager, Some(&connection)).await;
+        log_message("UPower appeared on the bus, switching back from sysfs polling");
+    }
+}
+
+/// Emit an AC event if `on_ac` differs from the state the manager knows about
+async fn apply_power_source(manager: &Arc<Mutex<Manager>>, on_ac: bool) {
+    let last_on_ac = !manager.lock().await.state.on_battery().unwrap_or(false);
+    if on_ac == last_on_ac {
+        return;
+    }
+
+    log_message(&format!(
+        "Power source changed: {}",
+        if on_ac { "AC" } else { "Battery" }
+    ));
+
+    // Emit event instead of mutating state directly
+    if on_ac {
+        handle_event(manager, Event::ACConnected).await;
+    } else {
+        handle_event(manager, Event::ACDisconnected).await;
+    }
+}
+
+/// Follow UPower's OnBattery property and the display device charge.
+/// Returns an error if UPower can't be reached, and Ok once it leaves the bus.
+async fn watch_upower(manager: &Arc<Mutex<Manager>>, connection: &Connection) -> zbus::Result<()> {
+    let upower = Proxy::new(
+        connection,
+        UPOWER_SERVICE,
+        "/org/freedesktop/UPower",
+        "org.freedesktop.UPower",
+    ).await?;
+    let display_device = Proxy::new(
+        connection,
+        UPOWER_SERVICE,
+        "/org/freedesktop/UPower/devices/DisplayDevice",
+        "org.freedesktop.UPower.Device",
+    ).await?;
+
+    // Fails if UPower isn't running, before anything is subscribed
+    let on_battery: bool = upower.get_property("OnBattery").await?;
+    apply_power_source(manager, !on_battery).await;
+
+    // Without a battery the display device reports 0%, which must not count as critical
+    let has_battery = display_device.get_property::<bool>("IsPresent").await.unwrap_or(false);
+
+    let mut on_battery_changes = upower.receive_property_changed::<bool>("OnBattery").await;
+    let mut percentage_changes = display_device.receive_property_changed::<f64>("Percentage").await;
+    let mut owner_changes = upower.receive_owner_changed().await?;
+
+    log_message("Watching UPower for power source changes");
+
+    loop {
+        tokio::select! {
+            Some(change) = on_battery_changes.next() => {
+                if let Ok(on_battery) = change.get().await {
+                    apply_power_source(manager, !on_battery).await;
+                }
+            }
+            Some(change) = percentage_changes.next(), if has_battery => {
+                if let Ok(percentage) = change.get().await {
+                    let capacity = percentage.round().clamp(0.0, 100.0) as u8;
+                    handle_event(manager, Event::BatteryCapacity(capacity)).await;
+                }
+            }
+            owner = owner_changes.next() => {
+                if !matches!(owner, Some(Some(_))) {
+                    return Ok(());
+                }
+            }
         }
-        drop(mgr);
-
-        let on_ac = is_on_ac_power().await;
-
-        if on_ac != last_on_ac {
-            last_on_ac = on_ac;
-            log_message(&format!(
-                "Power source changed: {}",
-                if on_ac { "AC" } else { "Battery" }
-            ));
-
-            // Emit event instead of mutating state directly
-            if on_ac {
-                handle_event(&manager, Event::ACConnected).await;
-            } else {
-                handle_event(&manager, Event::ACDisconnected).await;
+    }
+}
+
+/// Poll sysfs for power source and charge changes until UPower takes a name
+/// owner on the system bus. Without a system bus this polls for good.
+async fn poll_power_source(manager: &Arc<Mutex<Manager>>, system_bus: Option<&Connection>) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(5));
+
+    let mut owner_changes = match system_bus {
+        Some(connection) => match DBusProxy::new(connection).await {
+            Ok(dbus) => dbus.receive_name_owner_changed_with_args(&[(0, UPOWER_SERVICE)]).await.ok(),
+            Err(_) => None,
+        },
+        None => None,
+    };
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {
+                if let Some(capacity) = read_battery_capacity() {
+                    handle_event(manager, Event::BatteryCapacity(capacity)).await;
+                }
+
+                apply_power_source(manager, is_on_ac_power().await).await;
+            }
+            Some(signal) = async { owner_changes.as_mut()?.next().await }, if owner_changes.is_some() => {
+                if signal.args().is_ok_and(|args| args.new_owner().is_some()) {
+                    return;
+                }
             }
         }
     }
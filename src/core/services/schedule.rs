@@ -0,0 +1,49 @@
+use std::{sync::Arc, time::Duration};
+use tokio::{sync::Mutex, time::sleep};
+
+use crate::{
+    core::manager::{helpers::apply_schedule, Manager},
+    log::log_message,
+};
+
+/// Upper bound between evaluations. Timers don't advance while suspended and
+/// the wall clock can jump, so never trust a single long sleep to a boundary.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// Re-evaluate the schedule at every rule boundary, independent of user activity
+pub async fn spawn_schedule_task(manager: Arc<Mutex<Manager>>) {
+    let (has_rules, shutdown) = {
+        let mut mgr = manager.lock().await;
+        apply_schedule(&mut mgr).await;
+        let has_rules = mgr.state.cfg.as_ref().is_some_and(|c| !c.schedule.is_empty());
+        (has_rules, mgr.state.shutdown_flag.clone())
+    };
+
+    if has_rules {
+        log_message("Schedule rules loaded, watching for rule boundaries");
+    }
+
+    tokio::spawn(async move {
+        loop {
+            // Rules may change on reload, so look them up on every pass
+            let wait = {
+                let mgr = manager.lock().await;
+                let now = chrono::Local::now().naive_local();
+                mgr.state.cfg.as_ref()
+                    .and_then(|c| c.schedule.iter().filter_map(|r| r.next_boundary(now)).min())
+                    .and_then(|next| (next - now).to_std().ok())
+                    .map_or(MAX_SLEEP, |d| d.min(MAX_SLEEP))
+            };
+
+            tokio::select! {
+                _ = sleep(wait) => {
+                    let mut mgr = manager.lock().await;
+                    apply_schedule(&mut mgr).await;
+                }
+                _ = shutdown.notified() => break,
+            }
+        }
+
+        log_message("Schedule task shutting down...");
+    });
+}
@@ -0,0 +1,208 @@
+use std::{collections::HashMap, sync::Arc};
+use eyre::Result;
+use futures::StreamExt;
+use tokio::sync::Mutex;
+use zbus::{
+    fdo::{DBusProxy, RequestNameFlags, RequestNameReply},
+    interface,
+    message::Header,
+    names::BusName,
+    Connection,
+};
+
+use crate::core::manager::{helpers::set_inhibitor, state::Inhibitor, Manager};
+use crate::log::{log_error_message, log_message};
+
+const SERVICE_NAME: &str = "org.freedesktop.ScreenSaver";
+/// The spec'd path plus the one KDE and Chromium use
+const OBJECT_PATHS: &[&str] = &["/org/freedesktop/ScreenSaver", "/ScreenSaver"];
+
+#[derive(Debug, Clone)]
+struct ScreenSaverInhibit {
+    sender: String,
+    application: String,
+    reason: String,
+}
+
+#[derive(Debug, Default)]
+struct InhibitTable {
+    next_cookie: u32,
+    inhibits: HashMap<u32, ScreenSaverInhibit>,
+}
+
+struct ScreenSaver {
+    manager: Arc<Mutex<Manager>>,
+    table: Arc<Mutex<InhibitTable>>,
+}
+
+#[interface(name = "org.freedesktop.ScreenSaver")]
+impl ScreenSaver {
+    async fn inhibit(
+        &self,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(connection)] conn: &Connection,
+        application_name: String,
+        reason_for_inhibit: String,
+    ) -> u32 {
+        let sender = header.sender().map(|s| s.to_string()).unwrap_or_default();
+
+        let cookie = {
+            let mut table = self.table.lock().await;
+            // Cookies are never 0, some clients treat that as "no inhibit"
+            table.next_cookie = table.next_cookie.wrapping_add(1).max(1);
+            let cookie = table.next_cookie;
+            table.inhibits.insert(cookie, ScreenSaverInhibit {
+                sender: sender.clone(),
+                application: application_name.clone(),
+                reason: reason_for_inhibit.clone(),
+            });
+            cookie
+        };
+
+        log_message(&format!(
+            "ScreenSaver inhibit #{} from {} ({}): {}",
+            cookie, application_name, sender, reason_for_inhibit
+        ));
+
+        {
+            let mut mgr = self.manager.lock().await;
+            let scope = mgr.state.cfg.as_ref().map(|c| c.inhibit_scopes.screensaver.clone()).unwrap_or_default();
+            let reason = format!("{}: {}", application_name, reason_for_inhibit);
+            set_inhibitor(&mut mgr, &inhibitor_source(cookie), Some(Inhibitor::new(scope, reason))).await;
+        }
+
+        // The client may have left before the inhibit was recorded, when its
+        // NameOwnerChanged had nothing to release yet
+        if !sender_on_bus(conn, &sender).await {
+            release_sender(&self.manager, &self.table, &sender).await;
+        }
+        cookie
+    }
+
+    async fn un_inhibit(&self, #[zbus(header)] header: Header<'_>, cookie: u32) {
+        let sender = header.sender().map(|s| s.to_string()).unwrap_or_default();
+
+        let removed = {
+            let mut table = self.table.lock().await;
+            match table.inhibits.get(&cookie) {
+                // Only the client that took the inhibit may release it
+                Some(inhibit) if inhibit.sender != sender => {
+                    log_message(&format!(
+                        "ScreenSaver UnInhibit for cookie #{} from {} ignored: held by {}",
+                        cookie, sender, inhibit.sender
+                    ));
+                    return;
+                }
+                Some(_) => table.inhibits.remove(&cookie),
+                None => None,
+            }
+        };
+
+        match removed {
+            Some(inhibit) => {
+                log_message(&format!(
+                    "ScreenSaver inhibit #{} from {} released ({})",
+                    cookie, inhibit.application, inhibit.reason
+                ));
+                let mut mgr = self.manager.lock().await;
+                set_inhibitor(&mut mgr, &inhibitor_source(cookie), None).await;
+            }
+            None => log_message(&format!("ScreenSaver UnInhibit for unknown cookie #{}", cookie)),
+        }
+    }
+
+    async fn get_active(&self) -> bool {
+        self.manager.lock().await.state.lock_state.is_locked
+    }
+}
+
+/// Every cookie is its own inhibitor with the manager
+fn inhibitor_source(cookie: u32) -> String {
+    format!("screensaver.{}", cookie)
+}
+
+/// Whether `sender` still has a connection to the bus
+async fn sender_on_bus(conn: &Connection, sender: &str) -> bool {
+    let Ok(name) = BusName::try_from(sender) else { return false };
+    match DBusProxy::new(conn).await {
+        Ok(dbus) => dbus.name_has_owner(name).await.unwrap_or(true),
+        // Can't tell; NameOwnerChanged still releases it
+        Err(_) => true,
+    }
+}
+
+/// Drop every inhibit held by a client that left the bus
+async fn release_sender(manager: &Arc<Mutex<Manager>>, table: &Arc<Mutex<InhibitTable>>, sender: &str) {
+    let released: Vec<(u32, ScreenSaverInhibit)> = {
+        let mut table = table.lock().await;
+        let cookies: Vec<u32> = table.inhibits.iter()
+            .filter(|(_, i)| i.sender == sender)
+            .map(|(c, _)| *c)
+            .collect();
+        cookies.into_iter()
+            .filter_map(|c| table.inhibits.remove(&c).map(|i| (c, i)))
+            .collect()
+    };
+
+    if released.is_empty() {
+        return;
+    }
+
+    let mut mgr = manager.lock().await;
+    for (cookie, inhibit) in released {
+        log_message(&format!(
+            "ScreenSaver inhibit #{} from {} released: client disconnected",
+            cookie, inhibit.application
+        ));
+        set_inhibitor(&mut mgr, &inhibitor_source(cookie), None).await;
+    }
+}
+
+/// Serve org.freedesktop.ScreenSaver on the session bus so apps inhibiting
+/// through it (browsers, video players) pause the idle timers. Does nothing
+/// if another service already owns the name.
+pub async fn spawn_screensaver_service(manager: Arc<Mutex<Manager>>) -> Result<()> {
+    let conn = Connection::session().await?;
+    let table = Arc::new(Mutex::new(InhibitTable::default()));
+
+    // Subscribed before serving, so no client can leave unnoticed in between
+    let dbus = DBusProxy::new(&conn).await?;
+    let mut owner_changes = dbus.receive_name_owner_changed().await?;
+
+    for path in OBJECT_PATHS {
+        let iface = ScreenSaver { manager: Arc::clone(&manager), table: Arc::clone(&table) };
+        conn.object_server().at(*path, iface).await?;
+    }
+
+    match conn.request_name_with_flags(SERVICE_NAME, RequestNameFlags::DoNotQueue.into()).await? {
+        RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner => {
+            log_message("Serving org.freedesktop.ScreenSaver inhibition on the session bus");
+        }
+        _ => {
+            log_message("org.freedesktop.ScreenSaver is owned by another service, not serving it");
+            return Ok(());
+        }
+    }
+
+    tokio::spawn(async move {
+        // Keep the connection (and with it the service) alive for the task's lifetime
+        let _conn = conn;
+
+        while let Some(signal) = owner_changes.next().await {
+            let args = match signal.args() {
+                Ok(args) => args,
+                Err(e) => {
+                    log_error_message(&format!("Failed to parse NameOwnerChanged: {}", e));
+                    continue;
+                }
+            };
+
+            // A unique name losing its owner means the client disconnected
+            if args.new_owner().is_none() && args.name().starts_with(':') {
+                release_sender(&manager, &table, args.name().as_str()).await;
+            }
+        }
+    });
+
+    Ok(())
+}
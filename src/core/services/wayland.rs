@@ -1,34 +1,63 @@
 use eyre::Result;
+use std::io::ErrorKind;
+use std::os::fd::{AsRawFd, RawFd};
 use std::sync::Arc;
 use std::time::Duration;
 
-use crate::core::manager::Manager;
+use crate::core::events::handlers::{handle_event, Event};
+use crate::core::manager::{helpers::{set_compositor_manager, set_wayland_inhibited, wake_idle_tasks}, Manager};
+use crate::core::services::input::spawn_input_task;
 use crate::log::{log_error_message, log_message};
 
+use tokio::io::unix::AsyncFd;
 use tokio::sync::Notify;
 use tokio::time::sleep;
 
 use wayland_client::{
-    protocol::{wl_registry, wl_seat::WlSeat},
-    Connection, Dispatch, QueueHandle,
+    backend::WaylandError,
+    protocol::{wl_output::WlOutput, wl_registry, wl_seat::WlSeat},
+    Connection, Dispatch, EventQueue, QueueHandle,
 };
 use wayland_protocols::ext::idle_notify::v1::client::{
     ext_idle_notifier_v1::ExtIdleNotifierV1,
     ext_idle_notification_v1::{ExtIdleNotificationV1, Event as IdleEvent},
 };
-use wayland_protocols::wp::idle_inhibit::zv1::client::{
-    zwp_idle_inhibit_manager_v1::{ZwpIdleInhibitManagerV1, Event as InhibitMgrEvent},
-    zwp_idle_inhibitor_v1::{ZwpIdleInhibitorV1, Event as InhibitorEvent},
+use wayland_protocols_wlr::output_power_management::v1::client::{
+    zwlr_output_power_manager_v1::ZwlrOutputPowerManagerV1,
+    zwlr_output_power_v1::{Event as OutputPowerEvent, Mode as OutputPowerMode, ZwlrOutputPowerV1},
 };
 
+/// Upper bound for the activity notification, shortened to the first stage
+const IDLE_TIMEOUT_MS: u32 = 5_000;
+/// How much longer the input probe waits than the activity notification, so
+/// the inhibitor-respecting one has always had its chance to fire first
+const INPUT_PROBE_DELAY_MS: u32 = 1_000;
+
+/// Which idle notification an event belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    /// Drives idle/activity handling; honours compositor inhibitors when
+    /// respect_wayland_inhibitors is set
+    Idle,
+    /// Ignores inhibitors; only used to tell when the compositor is inhibiting
+    InputProbe,
+    /// Reached the idle time of the action at this index in the active block
+    Stage(usize),
+}
+
 pub struct WaylandIdleData {
     pub manager: Arc<tokio::sync::Mutex<Manager>>,
     pub idle_notifier: Option<ExtIdleNotifierV1>,
+    pub notifier_version: u32,
     pub seat: Option<WlSeat>,
     pub notification: Option<ExtIdleNotificationV1>,
-    pub inhibit_manager: Option<ZwpIdleInhibitManagerV1>,
-    pub active_inhibitors: u32,
+    pub input_probe: Option<ExtIdleNotificationV1>,
+    pub stage_notifications: Vec<ExtIdleNotificationV1>,
     pub respect_inhibitors: bool,
+    /// Whether the Idle notification currently reports idle
+    pub idled: bool,
+    /// Whether the InputProbe notification currently reports idle
+    pub input_idled: bool,
     pub shutdown: Arc<Notify>,
 }
 
@@ -37,17 +66,64 @@ impl WaylandIdleData {
         Self {
             manager,
             idle_notifier: None,
+            notifier_version: 0,
             seat: None,
             notification: None,
-            inhibit_manager: None,
-            active_inhibitors: 0,
+            input_probe: None,
+            stage_notifications: Vec::new(),
             respect_inhibitors,
+            idled: false,
+            input_idled: false,
             shutdown: Arc::new(Notify::new()),
         }
     }
 
-    pub fn is_inhibited(&self) -> bool {
-        self.respect_inhibitors && self.active_inhibitors > 0
+    /// (Re)create the idle notifications for the given stage timeouts.
+    /// get_input_idle_notification (v2) ignores inhibitors, get_idle_notification
+    /// doesn't; when respecting inhibitors both are used to detect inhibition.
+    /// Returns false if the compositor lacks ext_idle_notifier_v1 or a seat.
+    fn create_notifications(&mut self, stages: &[(usize, u32)], qh: &QueueHandle<Self>) -> bool {
+        let (Some(notifier), Some(seat)) = (&self.idle_notifier, &self.seat) else {
+            return false;
+        };
+
+        for old in self.notification.take().into_iter()
+            .chain(self.input_probe.take())
+            .chain(self.stage_notifications.drain(..))
+        {
+            old.destroy();
+        }
+        self.idled = false;
+        self.input_idled = false;
+
+        // Only input-idle notifications ignore inhibitors, and they need v2
+        let honour_inhibitors = self.respect_inhibitors || self.notifier_version < 2;
+        let create = |timeout: u32, kind: NotificationKind, honour: bool| {
+            if honour {
+                notifier.get_idle_notification(timeout, seat, qh, kind)
+            } else {
+                notifier.get_input_idle_notification(timeout, seat, qh, kind)
+            }
+        };
+
+        // Idle before (or with) the first stage, so its Resumed covers all activity
+        let idle_timeout = stages.first().map_or(IDLE_TIMEOUT_MS, |(_, t)| (*t).clamp(1, IDLE_TIMEOUT_MS));
+        self.notification = Some(create(idle_timeout, NotificationKind::Idle, honour_inhibitors));
+
+        if self.respect_inhibitors && self.notifier_version >= 2 {
+            self.input_probe = Some(create(
+                idle_timeout + INPUT_PROBE_DELAY_MS,
+                NotificationKind::InputProbe,
+                false,
+            ));
+        }
+
+        self.stage_notifications = stages
+            .iter()
+            .map(|(index, timeout)| create(*timeout, NotificationKind::Stage(*index), honour_inhibitors))
+            .collect();
+
+        true
     }
 }
 
@@ -62,22 +138,19 @@ impl Dispatch<wl_registry::WlRegistry, ()> for WaylandIdleData {
         _: &Connection,
         qh: &QueueHandle<Self>,
     ) {
-        if let wl_registry::Event::Global { name, interface, .. } = event {
+        if let wl_registry::Event::Global { name, interface, version } = event {
             match interface.as_str() {
                 "ext_idle_notifier_v1" => {
+                    let version = version.min(2);
                     state.idle_notifier =
-                        Some(registry.bind::<ExtIdleNotifierV1, _, _>(name, 1, qh, ()));
-                    log_message("Binding ext_idle_notifier_v1");
+                        Some(registry.bind::<ExtIdleNotifierV1, _, _>(name, version, qh, ()));
+                    state.notifier_version = version;
+                    log_message(&format!("Binding ext_idle_notifier_v1 (v{})", version));
                 }
                 "wl_seat" => {
                     state.seat = Some(registry.bind::<WlSeat, _, _>(name, 1, qh, ()));
                     log_message("Binding wl_seat");
                 }
-                "zwp_idle_inhibit_manager_v1" => {
-                    state.inhibit_manager =
-                        Some(registry.bind::<ZwpIdleInhibitManagerV1, _, _>(name, 1, qh, ()));
-                    log_message("Binding zwp_idle_inhibit_manager_v1");
-                }
                 _ => {}
             }
         }
@@ -95,34 +168,74 @@ impl Dispatch<ExtIdleNotifierV1, ()> for WaylandIdleData {
     ) {}
 }
 
-impl Dispatch<ExtIdleNotificationV1, ()> for WaylandIdleData {
+impl Dispatch<ExtIdleNotificationV1, NotificationKind> for WaylandIdleData {
     fn event(
         state: &mut Self,
         _: &ExtIdleNotificationV1,
         event: IdleEvent,
-        _: &(),
+        kind: &NotificationKind,
         _: &Connection,
         _: &QueueHandle<Self>,
     ) {
         let manager = Arc::clone(&state.manager);
-        let inhibited = state.is_inhibited();
 
-        tokio::spawn(async move {
-            if inhibited {
-                log_message("Idle inhibited; skipping idle trigger");
-                return;
+        if let NotificationKind::Stage(index) = *kind {
+            // Activity is reported by the Idle notification, which idles first
+            if let IdleEvent::Idled = event {
+                tokio::spawn(async move {
+                    let mut mgr = manager.lock().await;
+                    log_message(&format!("Compositor reports idle stage {} reached", index));
+                    mgr.state.compositor_stage = Some(mgr.state.compositor_stage.map_or(index, |s| s.max(index)));
+                    mgr.check_timeouts().await;
+                    wake_idle_tasks(&mgr.state);
+                });
             }
+            return;
+        }
 
-            let mut mgr = manager.lock().await;
+        if *kind == NotificationKind::InputProbe {
+            // The user has been idle longer than the Idle notification's timeout;
+            // if that one stayed quiet, an inhibitor is holding it back
+            let inhibited = match event {
+                IdleEvent::Idled => {
+                    state.input_idled = true;
+                    !state.idled
+                }
+                _ => {
+                    state.input_idled = false;
+                    false
+                }
+            };
+            tokio::spawn(async move {
+                let mut mgr = manager.lock().await;
+                set_wayland_inhibited(&mut mgr, inhibited).await;
+            });
+            return;
+        }
 
+        match event {
+            IdleEvent::Idled => state.idled = true,
+            IdleEvent::Resumed => state.idled = false,
+            _ => {}
+        }
+        // Resumed without input: an inhibitor appeared while we were idle
+        let inhibitor_appeared = state.input_idled && matches!(event, IdleEvent::Resumed);
+
+        tokio::spawn(async move {
             match event {
                 IdleEvent::Idled => {
                     log_message("Compositor detected idle");
-                    mgr.check_timeouts().await;
+                    // Reaching idle means nothing inhibits us any more
+                    let mut mgr = manager.lock().await;
+                    set_wayland_inhibited(&mut mgr, false).await;
+                }
+                IdleEvent::Resumed if inhibitor_appeared => {
+                    let mut mgr = manager.lock().await;
+                    set_wayland_inhibited(&mut mgr, true).await;
                 }
                 IdleEvent::Resumed => {
                     log_message("Compositor detected activity");
-                    mgr.reset().await;
+                    handle_event(&manager, Event::InputActivity).await;
                 }
                 _ => {}
             }
@@ -130,37 +243,6 @@ impl Dispatch<ExtIdleNotificationV1, ()> for WaylandIdleData {
     }
 }
 
-
-impl Dispatch<ZwpIdleInhibitorV1, ()> for WaylandIdleData {
-    fn event(
-        state: &mut Self,
-        _proxy: &ZwpIdleInhibitorV1,
-        _event: InhibitorEvent,
-        _: &(),
-        _: &Connection,
-        _: &QueueHandle<Self>,
-    ) {
-        state.active_inhibitors += 1;
-        log_message(&format!("Inhibitor created, count={}", state.active_inhibitors));
-    }
-}
-
-impl Dispatch<ZwpIdleInhibitManagerV1, ()> for WaylandIdleData {
-    fn event(
-        state: &mut Self,
-        _proxy: &ZwpIdleInhibitManagerV1,
-        _event: InhibitMgrEvent,
-        _: &(),
-        _: &Connection,
-        _: &QueueHandle<Self>,
-    ) {
-        if state.active_inhibitors > 0 {
-            state.active_inhibitors -= 1;
-            log_message(&format!("Inhibitor removed, count={}", state.active_inhibitors));
-        }
-    }
-}
-
 impl Dispatch<WlSeat, ()> for WaylandIdleData {
     fn event(
         _: &mut Self,
@@ -173,69 +255,346 @@ impl Dispatch<WlSeat, ()> for WaylandIdleData {
 }
 
 
-pub async fn setup(
-    manager: Arc<tokio::sync::Mutex<Manager>>,
-    respect_inhibitors: bool,
-) -> Result<Arc<tokio::sync::Mutex<WaylandIdleData>>> {
-    log_message(&format!(
-        "Setting up Wayland idle detection (respect_inhibitors={})",
-        respect_inhibitors
-    ));
+/// How often to look for the compositor's socket after it went away
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A connection handle whose socket is what gets polled; holding it keeps
+/// the socket open for as long as tokio watches it
+struct ConnectionFd(Connection);
+
+impl AsRawFd for ConnectionFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.backend().poll_fd().as_raw_fd()
+    }
+}
+
+/// Register the connection's socket with tokio so the event loop can sleep
+/// until the compositor has something for us
+pub fn watch_connection(conn: &Connection) -> std::io::Result<AsyncFd<impl AsRawFd>> {
+    // SAFETY: the backend owns the socket and keeps the same fd until the last
+    // connection handle, including the one moved in here, is dropped
+    Ok(unsafe { AsyncFd::register(ConnectionFd(conn.clone())) }?)
+}
+
+/// One connection to the compositor with its globals bound
+struct WaylandSession {
+    conn: Connection,
+    event_queue: EventQueue<WaylandIdleData>,
+    qh: QueueHandle<WaylandIdleData>,
+    data: WaylandIdleData,
+    managed: bool,
+}
+
+/// Why an event loop run ended
+enum SessionEnd {
+    Shutdown,
+    Disconnected(String),
+}
 
-    // Connect to Wayland
+/// Connect, bind globals and request the idle notifications for the
+/// active block's stages
+async fn connect(manager: &Arc<tokio::sync::Mutex<Manager>>, respect_inhibitors: bool) -> Result<WaylandSession> {
     let conn = Connection::connect_to_env()
         .map_err(|e| eyre::eyre!("Failed to connect to Wayland: {}", e))?;
     let mut event_queue = conn.new_event_queue();
     let qh = event_queue.handle();
-    let display = conn.display();
 
-    // Initialize WaylandIdleData
-    let mut app_data = WaylandIdleData::new(manager.clone(), respect_inhibitors);
+    let mut data = WaylandIdleData::new(Arc::clone(manager), respect_inhibitors);
 
     // Bind globals
-    let _registry = display.get_registry(&qh, ());
-    event_queue.roundtrip(&mut app_data)?;
-
-    // Request idle notification if both notifier and seat are available
-    if let (Some(notifier), Some(seat)) = (&app_data.idle_notifier, &app_data.seat) {
-        let timeout_ms = 5_000; // placeholder, can be dynamic
-        let notification = notifier.get_idle_notification(timeout_ms, seat, &qh, ());
-        app_data.notification = Some(notification);
-        log_message("Wayland idle detection active");
+    let _registry = conn.display().get_registry(&qh, ());
+    event_queue.roundtrip(&mut data)?;
+
+    // Request idle notifications if both notifier and seat are available;
+    // the compositor then drives the idle timers instead of libinput
+    let stages = manager.lock().await.state.stage_timeouts();
+    let managed = data.create_notifications(&stages, &qh);
+    if managed {
+        conn.flush()?;
+        log_message(&format!("Wayland idle detection active ({} stage notification(s))", stages.len()));
+        if data.notifier_version < 2 {
+            log_message("ext_idle_notifier_v1 v1 only, inhibitors can be neither ignored nor reported");
+        }
     }
+    set_compositor_manager(&mut manager.lock().await.state, managed);
+
+    Ok(WaylandSession { conn, event_queue, qh, data, managed })
+}
 
-    // Wrap in Arc<Mutex>
-    let app_data = Arc::new(tokio::sync::Mutex::new(app_data));
+/// Dispatch events until shutdown or until the compositor hangs up
+async fn run_session(session: &mut WaylandSession, manager: &Arc<tokio::sync::Mutex<Manager>>) -> SessionEnd {
+    let WaylandSession { conn, event_queue, qh, data, managed } = session;
 
-    let shutdown_flag = {
+    let (stages_changed, shutdown_flag) = {
         let mgr = manager.lock().await;
-        Arc::clone(&mgr.state.shutdown_flag)
+        (Arc::clone(&mgr.state.stages_changed), Arc::clone(&mgr.state.shutdown_flag))
+    };
+
+    let fd = match watch_connection(conn) {
+        Ok(fd) => fd,
+        Err(e) => return SessionEnd::Disconnected(format!("cannot watch Wayland socket: {}", e)),
     };
 
-    // Event loop — safe and cooperative shutdown
+    loop {
+        if let Err(e) = event_queue.dispatch_pending(data) {
+            return SessionEnd::Disconnected(e.to_string());
+        }
+        match conn.flush() {
+            Err(WaylandError::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => return SessionEnd::Disconnected(e.to_string()),
+            Ok(()) => {}
+        }
+
+        // None means events are already queued, dispatch those first
+        let Some(guard) = event_queue.prepare_read() else {
+            continue;
+        };
+
+        tokio::select! {
+            _ = shutdown_flag.notified() => return SessionEnd::Shutdown,
+            // Block switch, reload or restarted timers
+            _ = stages_changed.notified(), if *managed => {
+                drop(guard);
+                let (stages, respect) = {
+                    let mut mgr = manager.lock().await;
+                    // Stages reached so far belong to the old notifications
+                    mgr.state.compositor_stage = None;
+                    let respect = mgr.state.cfg.as_ref().map(|c| c.respect_wayland_inhibitors);
+                    (mgr.state.stage_timeouts(), respect)
+                };
+                if let Some(respect) = respect {
+                    data.respect_inhibitors = respect;
+                }
+                data.create_notifications(&stages, qh);
+            }
+            ready = fd.readable() => {
+                let mut ready = match ready {
+                    Ok(ready) => ready,
+                    Err(e) => return SessionEnd::Disconnected(e.to_string()),
+                };
+                match guard.read() {
+                    Ok(_) => {}
+                    Err(WaylandError::Io(e)) if e.kind() == ErrorKind::WouldBlock => ready.clear_ready(),
+                    Err(e) => return SessionEnd::Disconnected(e.to_string()),
+                }
+            }
+        }
+    }
+}
+
+/// Run libinput only while the compositor can't report idle stages
+pub async fn sync_input_fallback(manager: &Arc<tokio::sync::Mutex<Manager>>) {
+    let mut mgr = manager.lock().await;
+    if mgr.state.compositor_managed {
+        if let Some(handle) = mgr.input_task_handle.take() {
+            log_message("Compositor reports idle stages again, stopping libinput");
+            handle.abort();
+        }
+    } else if mgr.input_task_handle.is_none() {
+        log_message("Compositor lacks ext_idle_notifier_v1, falling back to libinput");
+        mgr.input_task_handle = Some(spawn_input_task(Arc::clone(manager)));
+    }
+}
+
+/// Forget what the old compositor told us; its inhibitors and idle state
+/// died with it
+async fn compositor_lost(manager: &Arc<tokio::sync::Mutex<Manager>>) {
+    let mut mgr = manager.lock().await;
+    set_wayland_inhibited(&mut mgr, false).await;
+    mgr.state.compositor_stage = None;
+}
+
+/// Try the compositor's socket until it accepts us again.
+/// Returns None if stasis shuts down in the meantime.
+async fn reconnect(manager: &Arc<tokio::sync::Mutex<Manager>>) -> Option<WaylandSession> {
+    let shutdown_flag = Arc::clone(&manager.lock().await.state.shutdown_flag);
+    let display = std::env::var("WAYLAND_DISPLAY").unwrap_or_default();
+    log_message(&format!("Waiting for the compositor to come back on {}...", display));
+
+    loop {
+        tokio::select! {
+            _ = shutdown_flag.notified() => return None,
+            _ = sleep(RECONNECT_INTERVAL) => {}
+        }
+
+        // Re-read the setting, the config may have been reloaded meanwhile
+        let respect = {
+            let mgr = manager.lock().await;
+            mgr.state.cfg.as_ref().is_none_or(|c| c.respect_wayland_inhibitors)
+        };
+        match connect(manager, respect).await {
+            Ok(session) => return Some(session),
+            // The socket isn't there yet, or the compositor is still starting up
+            Err(_) => continue,
+        }
+    }
+}
+
+/// Connect to the compositor and keep following it across restarts.
+/// The returned Notify fires if it goes away and `exit_with_compositor` is set.
+pub async fn setup(
+    manager: Arc<tokio::sync::Mutex<Manager>>,
+    respect_inhibitors: bool,
+) -> Result<Arc<Notify>> {
+    log_message(&format!(
+        "Setting up Wayland idle detection (respect_inhibitors={})",
+        respect_inhibitors
+    ));
+
+    let mut session = connect(&manager, respect_inhibitors).await?;
+    let compositor_gone = Arc::new(Notify::new());
+
     tokio::spawn({
-        let app_data = Arc::clone(&app_data);
+        let compositor_gone = Arc::clone(&compositor_gone);
         async move {
             log_message("Wayland event loop started");
             loop {
-                tokio::select! {
-                    _ = shutdown_flag.notified() => {
-                        break;
-                    }
-                    _ = sleep(Duration::from_millis(500)) => {
-                        let mut locked_data = app_data.lock().await;
-                        if let Err(e) = event_queue.dispatch_pending(&mut *locked_data) {
-                            log_error_message(&format!("Wayland event error: {}", e));
-                        }
-                    }
+                let reason = match run_session(&mut session, &manager).await {
+                    SessionEnd::Shutdown => break,
+                    SessionEnd::Disconnected(reason) => reason,
+                };
+                log_message(&format!("Lost connection to the compositor: {}", reason));
+
+                let exit = manager.lock().await.state.cfg.as_ref().is_some_and(|c| c.exit_with_compositor);
+                if exit {
+                    compositor_gone.notify_one();
+                    break;
                 }
+
+                compositor_lost(&manager).await;
+                session = match reconnect(&manager).await {
+                    Some(session) => session,
+                    None => break,
+                };
+                log_message("Reconnected to the compositor");
+                sync_input_fallback(&manager).await;
+
+                // A new compositor session counts as activity
+                manager.lock().await.reset().await;
             }
 
             log_message("Wayland event loop shutting down...");
         }
     });
 
-    Ok(app_data)
+    Ok(compositor_gone)
 }
 
+// --- Output power (DPMS) ---
 
+#[derive(Default)]
+struct OutputPowerData {
+    power_manager: Option<ZwlrOutputPowerManagerV1>,
+    outputs: Vec<WlOutput>,
+    failed: usize,
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for OutputPowerData {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        if let wl_registry::Event::Global { name, interface, .. } = event {
+            match interface.as_str() {
+                "zwlr_output_power_manager_v1" => {
+                    state.power_manager =
+                        Some(registry.bind::<ZwlrOutputPowerManagerV1, _, _>(name, 1, qh, ()));
+                }
+                "wl_output" => {
+                    state.outputs.push(registry.bind::<WlOutput, _, _>(name, 1, qh, ()));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Dispatch<WlOutput, ()> for OutputPowerData {
+    fn event(
+        _: &mut Self,
+        _: &WlOutput,
+        _: wayland_client::protocol::wl_output::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {}
+}
+
+impl Dispatch<ZwlrOutputPowerManagerV1, ()> for OutputPowerData {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrOutputPowerManagerV1,
+        _: <ZwlrOutputPowerManagerV1 as wayland_client::Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {}
+}
+
+impl Dispatch<ZwlrOutputPowerV1, ()> for OutputPowerData {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrOutputPowerV1,
+        event: OutputPowerEvent,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let OutputPowerEvent::Failed = event {
+            state.failed += 1;
+        }
+    }
+}
+
+/// Switch every output on or off through wlr-output-power-management.
+/// Returns `Ok(false)` if the compositor doesn't advertise the protocol.
+fn set_output_power_blocking(on: bool) -> Result<bool> {
+    let conn = Connection::connect_to_env()
+        .map_err(|e| eyre::eyre!("Failed to connect to Wayland: {}", e))?;
+    let mut event_queue = conn.new_event_queue();
+    let qh = event_queue.handle();
+
+    let mut data = OutputPowerData::default();
+    let _registry = conn.display().get_registry(&qh, ());
+    event_queue.roundtrip(&mut data)?;
+
+    let Some(power_manager) = data.power_manager.take() else {
+        return Ok(false);
+    };
+
+    let mode = if on { OutputPowerMode::On } else { OutputPowerMode::Off };
+    let controls: Vec<ZwlrOutputPowerV1> = data.outputs.iter()
+        .map(|output| {
+            let control = power_manager.get_output_power(output, &qh, ());
+            control.set_mode(mode);
+            control
+        })
+        .collect();
+
+    // Make sure the compositor has processed the requests before we disconnect;
+    // the mode sticks after the controls are destroyed
+    event_queue.roundtrip(&mut data)?;
+
+    for control in controls {
+        control.destroy();
+    }
+    power_manager.destroy();
+    conn.flush()?;
+
+    if data.failed > 0 {
+        log_error_message(&format!(
+            "{} of {} output(s) rejected power mode change",
+            data.failed, data.outputs.len()
+        ));
+    }
+
+    Ok(true)
+}
+
+pub async fn set_output_power(on: bool) -> Result<bool> {
+    tokio::task::spawn_blocking(move || set_output_power_blocking(on)).await?
+}
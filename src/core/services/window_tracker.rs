@@ -0,0 +1,746 @@
+use std::{
+    collections::HashMap,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::{atomic::{AtomicBool, Ordering}, Arc},
+    time::Duration,
+};
+use serde_json::Value;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+    sync::{Mutex, Notify},
+    time::sleep,
+};
+use wayland_client::{
+    backend::WaylandError,
+    event_created_child,
+    protocol::{wl_output::{self, WlOutput}, wl_registry},
+    Connection, Dispatch, Proxy, QueueHandle,
+};
+use wayland_protocols::ext::foreign_toplevel_list::v1::client::{
+    ext_foreign_toplevel_handle_v1::{Event as ExtHandleEvent, ExtForeignToplevelHandleV1},
+    ext_foreign_toplevel_list_v1::{self, ExtForeignToplevelListV1},
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::{
+    zwlr_foreign_toplevel_handle_v1::{Event as WlrHandleEvent, State as WlrState, ZwlrForeignToplevelHandleV1},
+    zwlr_foreign_toplevel_manager_v1::{self, ZwlrForeignToplevelManagerV1},
+};
+
+use crate::core::services::wayland::watch_connection;
+use crate::log::log_message;
+
+type IpcError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Wait between attempts to (re)connect to an event stream
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+/// A toplevel window as reported by the compositor
+#[derive(Debug, Clone, Default)]
+pub struct WindowInfo {
+    pub app_id: String,
+    pub title: String,
+    /// Not every protocol exposes the client's pid
+    pub pid: Option<u32>,
+    pub fullscreen: bool,
+    /// Has keyboard focus
+    pub focused: bool,
+    /// Workspace name, or its index where the compositor has no names
+    pub workspace: Option<String>,
+    /// Output (connector) name, e.g. DP-1
+    pub output: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Backend {
+    Hyprland,
+    Niri,
+    Sway,
+    /// wlr-foreign-toplevel-management or ext-foreign-toplevel-list, for
+    /// every other compositor
+    Toplevel,
+}
+
+impl Backend {
+    fn from_desktop(desktop: &str, need_fullscreen: bool) -> Self {
+        match desktop {
+            "hyprland" => Self::Hyprland,
+            // niri's IPC doesn't report fullscreen windows, its foreign-toplevel does
+            "niri" if need_fullscreen => Self::Toplevel,
+            "niri" => Self::Niri,
+            "sway" => Self::Sway,
+            _ => Self::Toplevel,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Hyprland => "Hyprland",
+            Self::Niri => "niri",
+            Self::Sway => "sway",
+            Self::Toplevel => "foreign-toplevel",
+        }
+    }
+
+    /// foreign-toplevel has no notion of workspaces
+    fn reports_workspaces(self) -> bool {
+        !matches!(self, Self::Toplevel)
+    }
+}
+
+/// The compositor doesn't offer this backend at all, no point in retrying
+#[derive(Debug)]
+struct Unsupported(&'static str);
+
+impl std::fmt::Display for Unsupported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl std::error::Error for Unsupported {}
+
+/// Open windows, kept up to date from the compositor's IPC event stream
+#[derive(Clone)]
+pub struct WindowTracker {
+    backend: Backend,
+    windows: Arc<Mutex<HashMap<String, WindowInfo>>>,
+    live: Arc<AtomicBool>,
+    /// Fires whenever a window opens, closes or changes
+    pub changed: Arc<Notify>,
+}
+
+impl WindowTracker {
+    fn new(backend: Backend) -> Self {
+        Self {
+            backend,
+            windows: Arc::new(Mutex::new(HashMap::new())),
+            live: Arc::new(AtomicBool::new(false)),
+            changed: Arc::new(Notify::new()),
+        }
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        self.backend.name()
+    }
+
+    /// Whether windows carry a workspace, so `workspace` rules can match
+    pub fn reports_workspaces(&self) -> bool {
+        self.backend.reports_workspaces()
+    }
+
+    /// The open windows, or None while the event stream is down
+    pub async fn windows(&self) -> Option<Vec<WindowInfo>> {
+        if !self.live.load(Ordering::Relaxed) {
+            return None;
+        }
+        Some(self.windows.lock().await.values().cloned().collect())
+    }
+
+    /// Start over from a full snapshot; marks the tracker live
+    async fn replace(&self, windows: HashMap<String, WindowInfo>) {
+        *self.windows.lock().await = windows;
+        self.live.store(true, Ordering::Relaxed);
+        self.changed.notify_one();
+    }
+
+    async fn disconnect(&self) {
+        self.live.store(false, Ordering::Relaxed);
+        self.windows.lock().await.clear();
+        self.changed.notify_one();
+    }
+}
+
+/// Follow window events of the running compositor, through its own IPC where
+/// we speak it and foreign-toplevel otherwise. The tracker reconnects on its
+/// own, e.g. after a compositor restart.
+pub fn spawn_window_tracker(desktop: &str, need_fullscreen: bool) -> WindowTracker {
+    let backend = Backend::from_desktop(desktop, need_fullscreen);
+    let tracker = WindowTracker::new(backend);
+
+    tokio::spawn({
+        let tracker = tracker.clone();
+        async move {
+            // Only log state changes, not every failed reconnect
+            let mut reported_down = false;
+            loop {
+                let result = match backend {
+                    Backend::Hyprland => run_hyprland(&tracker).await,
+                    Backend::Niri => run_niri(&tracker).await,
+                    Backend::Sway => run_sway(&tracker).await,
+                    Backend::Toplevel => run_toplevel(&tracker).await,
+                };
+
+                let was_live = tracker.live.load(Ordering::Relaxed);
+                tracker.disconnect().await;
+                if let Err(e) = &result
+                    && e.is::<Unsupported>()
+                {
+                    log_message(&format!("No window tracking available ({}), falling back to process names", e));
+                    break;
+                }
+                if was_live || !reported_down {
+                    let reason = result.err().map_or_else(|| "stream closed".to_string(), |e| e.to_string());
+                    log_message(&format!(
+                        "{} window events unavailable ({}), falling back to polling",
+                        backend.name(), reason
+                    ));
+                    reported_down = true;
+                }
+
+                sleep(RECONNECT_DELAY).await;
+            }
+        }
+    });
+
+    tracker
+}
+
+// --- Hyprland ---
+
+fn hyprland_socket(name: &str) -> Option<PathBuf> {
+    let signature = std::env::var("HYPRLAND_INSTANCE_SIGNATURE").ok()?;
+    // Hyprland moved its sockets from /tmp to the runtime dir in 0.40
+    let runtime = std::env::var("XDG_RUNTIME_DIR").ok().map(|dir| PathBuf::from(dir).join("hypr"));
+    runtime.into_iter()
+        .chain(std::iter::once(PathBuf::from("/tmp/hypr")))
+        .map(|dir| dir.join(&signature).join(name))
+        .find(|path| path.exists())
+}
+
+/// One request on Hyprland's command socket, which answers and hangs up
+async fn hyprland_request(request: &str) -> Result<String, IpcError> {
+    let path = hyprland_socket(".socket.sock").ok_or("Hyprland command socket not found")?;
+    let mut stream = UnixStream::connect(path).await?;
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    Ok(response)
+}
+
+/// Events carry addresses without the 0x that clients -j uses
+fn hyprland_address(address: &str) -> String {
+    address.trim_start_matches("0x").to_string()
+}
+
+/// Monitor ids to names, from `monitors -j`
+pub fn hyprland_monitors(monitors: &Value) -> HashMap<i64, String> {
+    monitors.as_array().into_iter().flatten()
+        .filter_map(|m| Some((m.get("id")?.as_i64()?, m.get("name")?.as_str()?.to_string())))
+        .collect()
+}
+
+/// A window from `clients -j`; clients only reference their monitor by id
+pub fn hyprland_client(client: &Value, monitors: &HashMap<i64, String>) -> Option<(String, WindowInfo)> {
+    let address = hyprland_address(client.get("address")?.as_str()?);
+    let app_id = client.get("class")?.as_str()?.to_string();
+    let title = client.get("title").and_then(Value::as_str).unwrap_or_default().to_string();
+    let pid = client.get("pid").and_then(Value::as_u64).and_then(|pid| u32::try_from(pid).ok());
+    // A bool before 0.41, a mode bitmask (2 = fullscreen) since
+    let fullscreen = client.get("fullscreen").is_some_and(|f| {
+        f.as_bool().unwrap_or(false) || f.as_u64().is_some_and(|mode| mode & 2 != 0)
+    });
+    let focused = client.get("focusHistoryID").and_then(Value::as_i64) == Some(0);
+    let workspace = client.get("workspace")
+        .and_then(|w| w.get("name"))
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let output = client.get("monitor")
+        .and_then(Value::as_i64)
+        .map(|m| monitors.get(&m).cloned().unwrap_or_else(|| m.to_string()));
+    Some((address, WindowInfo { app_id, title, pid, fullscreen, focused, workspace, output }))
+}
+
+/// Events after which the window list is fetched again; their payloads
+/// don't carry enough to update a single window in place
+const HYPRLAND_WINDOW_EVENTS: &[&str] = &[
+    "openwindow",
+    "closewindow",
+    "fullscreen",
+    "activewindowv2",
+    "movewindowv2",
+    "focusedmon",
+    "windowtitlev2",
+    "renameworkspace",
+];
+
+async fn hyprland_windows() -> Result<HashMap<String, WindowInfo>, IpcError> {
+    let clients: Vec<Value> = serde_json::from_str(&hyprland_request("j/clients").await?)?;
+    let monitors = hyprland_monitors(&serde_json::from_str(&hyprland_request("j/monitors").await?)?);
+    Ok(clients.iter().filter_map(|c| hyprland_client(c, &monitors)).collect())
+}
+
+async fn run_hyprland(tracker: &WindowTracker) -> Result<(), IpcError> {
+    let path = hyprland_socket(".socket2.sock").ok_or("Hyprland event socket not found")?;
+    // Subscribe before taking the snapshot so no window slips through in between
+    let events = UnixStream::connect(path).await?;
+
+    tracker.replace(hyprland_windows().await?).await;
+    log_message("Tracking windows via the Hyprland event socket");
+
+    let mut lines = BufReader::new(events).lines();
+    while let Some(line) = lines.next_line().await? {
+        let Some((event, _)) = line.split_once(">>") else {
+            continue;
+        };
+        if HYPRLAND_WINDOW_EVENTS.contains(&event) {
+            tracker.replace(hyprland_windows().await?).await;
+        }
+    }
+
+    Ok(())
+}
+
+// --- niri ---
+
+/// Workspace ids to their name (or index) and output, from the event
+/// stream or `niri msg --json workspaces`
+pub fn niri_workspaces(workspaces: &[Value]) -> HashMap<u64, (String, Option<String>)> {
+    workspaces.iter()
+        .filter_map(|w| {
+            let id = w.get("id")?.as_u64()?;
+            // Unnamed workspaces are only known by their index on the output
+            let name = w.get("name").and_then(Value::as_str).map(str::to_string)
+                .or_else(|| w.get("idx").and_then(Value::as_u64).map(|idx| idx.to_string()))?;
+            let output = w.get("output").and_then(Value::as_str).map(str::to_string);
+            Some((id, (name, output)))
+        })
+        .collect()
+}
+
+/// A window from the event stream or `niri msg --json windows`. niri's IPC
+/// doesn't report fullscreen windows, foreign-toplevel does.
+pub fn niri_window(window: &Value, workspaces: &HashMap<u64, (String, Option<String>)>) -> Option<(String, WindowInfo)> {
+    let id = window.get("id")?.as_u64()?.to_string();
+    // app_id and title are null for clients that never set one
+    let app_id = window.get("app_id").and_then(Value::as_str).unwrap_or_default().to_string();
+    let title = window.get("title").and_then(Value::as_str).unwrap_or_default().to_string();
+    let pid = window.get("pid").and_then(Value::as_u64).and_then(|pid| u32::try_from(pid).ok());
+    let focused = window.get("is_focused").and_then(Value::as_bool).unwrap_or(false);
+    let (workspace, output) = window.get("workspace_id")
+        .and_then(Value::as_u64)
+        .and_then(|id| workspaces.get(&id))
+        .map_or((None, None), |(name, output)| (Some(name.clone()), output.clone()));
+    Some((id, WindowInfo { app_id, title, pid, focused, workspace, output, ..Default::default() }))
+}
+
+/// Raw objects from the niri event stream; windows only reference their
+/// workspace by id, so the table is rebuilt from both on every change
+#[derive(Default)]
+struct NiriState {
+    windows: HashMap<u64, Value>,
+    workspaces: Vec<Value>,
+}
+
+impl NiriState {
+    /// Apply one event, returning whether anything window-related changed
+    fn apply(&mut self, event: &Value) -> bool {
+        let id_of = |v: &Value| v.get("id").and_then(Value::as_u64);
+
+        if let Some(windows) = event.get("WindowsChanged").and_then(|e| e.get("windows")).and_then(Value::as_array) {
+            self.windows = windows.iter().filter_map(|w| Some((id_of(w)?, w.clone()))).collect();
+        } else if let Some(window) = event.get("WindowOpenedOrChanged").and_then(|e| e.get("window")) {
+            let Some(id) = id_of(window) else {
+                return false;
+            };
+            // A newly focused window implies the rest lost focus
+            if window.get("is_focused").and_then(Value::as_bool) == Some(true) {
+                self.set_focus(Some(id));
+            }
+            self.windows.insert(id, window.clone());
+        } else if let Some(closed) = event.get("WindowClosed") {
+            return id_of(closed).is_some_and(|id| self.windows.remove(&id).is_some());
+        } else if let Some(focus) = event.get("WindowFocusChanged") {
+            // id is null when nothing has focus
+            self.set_focus(id_of(focus));
+        } else if let Some(workspaces) = event.get("WorkspacesChanged").and_then(|e| e.get("workspaces")).and_then(Value::as_array) {
+            self.workspaces = workspaces.clone();
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn set_focus(&mut self, focused: Option<u64>) {
+        for (id, window) in self.windows.iter_mut() {
+            if let Some(window) = window.as_object_mut() {
+                window.insert("is_focused".to_string(), Value::Bool(Some(*id) == focused));
+            }
+        }
+    }
+
+    fn snapshot(&self) -> HashMap<String, WindowInfo> {
+        let workspaces = niri_workspaces(&self.workspaces);
+        self.windows.values().filter_map(|w| niri_window(w, &workspaces)).collect()
+    }
+}
+
+async fn run_niri(tracker: &WindowTracker) -> Result<(), IpcError> {
+    let path = std::env::var("NIRI_SOCKET").map_err(|_| "NIRI_SOCKET is not set")?;
+    let mut stream = UnixStream::connect(path).await?;
+    stream.write_all(b"\"EventStream\"\n").await?;
+
+    let mut lines = BufReader::new(stream).lines();
+    let reply = lines.next_line().await?.ok_or("niri closed the connection")?;
+    let reply: Value = serde_json::from_str(&reply)?;
+    if reply.get("Ok").is_none() {
+        return Err(format!("niri refused the event stream: {}", reply).into());
+    }
+
+    let mut state = NiriState::default();
+    while let Some(line) = lines.next_line().await? {
+        let Ok(event) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+        if !state.apply(&event) {
+            continue;
+        }
+
+        // The stream opens with full snapshots of workspaces and windows
+        let first = !tracker.live.load(Ordering::Relaxed);
+        if first && event.get("WindowsChanged").is_none() {
+            continue;
+        }
+        tracker.replace(state.snapshot()).await;
+        if first {
+            log_message("Tracking windows via the niri event stream");
+        }
+    }
+
+    Ok(())
+}
+
+// --- sway ---
+
+const SWAY_MAGIC: &[u8] = b"i3-ipc";
+const SWAY_SUBSCRIBE: u32 = 2;
+const SWAY_GET_TREE: u32 = 4;
+
+async fn sway_send(stream: &mut UnixStream, kind: u32, payload: &[u8]) -> Result<(), IpcError> {
+    let mut message = SWAY_MAGIC.to_vec();
+    message.extend_from_slice(&(payload.len() as u32).to_ne_bytes());
+    message.extend_from_slice(&kind.to_ne_bytes());
+    message.extend_from_slice(payload);
+    stream.write_all(&message).await?;
+    Ok(())
+}
+
+async fn sway_read(stream: &mut UnixStream) -> Result<(u32, Value), IpcError> {
+    let mut header = [0u8; 14];
+    stream.read_exact(&mut header).await?;
+    if &header[..6] != SWAY_MAGIC {
+        return Err("malformed sway IPC message".into());
+    }
+    let len = u32::from_ne_bytes(header[6..10].try_into()?) as usize;
+    let kind = u32::from_ne_bytes(header[10..14].try_into()?);
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await?;
+    Ok((kind, serde_json::from_slice(&payload)?))
+}
+
+/// Views are the tree nodes with a client behind them; xwayland ones have
+/// a class instead of an app_id
+fn sway_window(node: &Value, workspace: Option<&str>, output: Option<&str>) -> Option<(String, WindowInfo)> {
+    let pid = node.get("pid")?.as_u64().and_then(|pid| u32::try_from(pid).ok());
+    let id = node.get("id")?.as_u64()?.to_string();
+    let app_id = node.get("app_id").and_then(Value::as_str)
+        .or_else(|| node.get("window_properties")?.get("class")?.as_str())
+        .unwrap_or_default()
+        .to_string();
+    let title = node.get("name").and_then(Value::as_str).unwrap_or_default().to_string();
+    // 1 = fullscreen on its output, 2 = global fullscreen
+    let fullscreen = node.get("fullscreen_mode").and_then(Value::as_u64).is_some_and(|mode| mode > 0);
+    let focused = node.get("focused").and_then(Value::as_bool).unwrap_or(false);
+    Some((id, WindowInfo {
+        app_id,
+        title,
+        pid,
+        fullscreen,
+        focused,
+        workspace: workspace.map(str::to_string),
+        output: output.map(str::to_string),
+    }))
+}
+
+fn sway_collect(
+    node: &Value,
+    workspace: Option<&str>,
+    output: Option<&str>,
+    windows: &mut HashMap<String, WindowInfo>,
+) {
+    let (workspace, output) = match node.get("type").and_then(Value::as_str) {
+        Some("output") => (workspace, node.get("name").and_then(Value::as_str)),
+        Some("workspace") => (node.get("name").and_then(Value::as_str), output),
+        _ => (workspace, output),
+    };
+    if let Some((id, window)) = sway_window(node, workspace, output) {
+        windows.insert(id, window);
+    }
+    for key in ["nodes", "floating_nodes"] {
+        for child in node.get(key).and_then(Value::as_array).into_iter().flatten() {
+            sway_collect(child, workspace, output, windows);
+        }
+    }
+}
+
+async fn sway_windows(request: &mut UnixStream) -> Result<HashMap<String, WindowInfo>, IpcError> {
+    sway_send(request, SWAY_GET_TREE, b"").await?;
+    let (_, tree) = sway_read(request).await?;
+    let mut windows = HashMap::new();
+    sway_collect(&tree, None, None, &mut windows);
+    Ok(windows)
+}
+
+async fn run_sway(tracker: &WindowTracker) -> Result<(), IpcError> {
+    let path = std::env::var("SWAYSOCK").map_err(|_| "SWAYSOCK is not set")?;
+
+    // Subscribe before taking the snapshot so no window slips through in between
+    let mut events = UnixStream::connect(&path).await?;
+    sway_send(&mut events, SWAY_SUBSCRIBE, br#"["window","workspace"]"#).await?;
+    let (_, reply) = sway_read(&mut events).await?;
+    if reply.get("success").and_then(Value::as_bool) != Some(true) {
+        return Err(format!("sway refused the subscription: {}", reply).into());
+    }
+
+    let mut request = UnixStream::connect(&path).await?;
+    tracker.replace(sway_windows(&mut request).await?).await;
+    log_message("Tracking windows via sway IPC");
+
+    // Window events don't say which output the container is on, so take a
+    // fresh tree for every window or workspace change
+    loop {
+        sway_read(&mut events).await?;
+        tracker.replace(sway_windows(&mut request).await?).await;
+    }
+}
+
+// --- foreign-toplevel ---
+
+#[derive(Default)]
+struct ToplevelData {
+    wlr_global: Option<(u32, u32)>,
+    ext_global: Option<(u32, u32)>,
+    /// Bound so output_enter/output_leave can reference them, with the
+    /// names wl_output v4 reports
+    outputs: HashMap<String, Option<String>>,
+    /// Changes received since the toplevel's last done event
+    pending: HashMap<String, WindowInfo>,
+    windows: HashMap<String, WindowInfo>,
+    dirty: bool,
+}
+
+impl ToplevelData {
+    fn output_name(&self, output: &WlOutput) -> String {
+        let key = toplevel_key(output);
+        self.outputs.get(&key).cloned().flatten().unwrap_or(key)
+    }
+
+    /// Properties are only sent when they change, so start from the known state
+    fn pending(&mut self, id: String) -> &mut WindowInfo {
+        let known = self.windows.get(&id).cloned().unwrap_or_default();
+        self.pending.entry(id).or_insert(known)
+    }
+
+    fn done(&mut self, id: String) {
+        if let Some(window) = self.pending.remove(&id) {
+            self.windows.insert(id, window);
+            self.dirty = true;
+        }
+    }
+
+    fn closed(&mut self, id: &str) {
+        self.pending.remove(id);
+        if self.windows.remove(id).is_some() {
+            self.dirty = true;
+        }
+    }
+}
+
+fn toplevel_key(proxy: &impl Proxy) -> String {
+    proxy.id().protocol_id().to_string()
+}
+
+impl Dispatch<wl_registry::WlRegistry, ()> for ToplevelData {
+    fn event(
+        state: &mut Self,
+        registry: &wl_registry::WlRegistry,
+        event: wl_registry::Event,
+        _: &(),
+        _: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        // Only note the managers here; which one gets bound is decided once all are known
+        if let wl_registry::Event::Global { name, interface, version } = event {
+            match interface.as_str() {
+                "zwlr_foreign_toplevel_manager_v1" => state.wlr_global = Some((name, version)),
+                "ext_foreign_toplevel_list_v1" => state.ext_global = Some((name, version)),
+                "wl_output" => {
+                    let output = registry.bind::<WlOutput, _, _>(name, version.min(4), qh, ());
+                    state.outputs.insert(toplevel_key(&output), None);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+impl Dispatch<WlOutput, ()> for ToplevelData {
+    fn event(
+        state: &mut Self,
+        output: &WlOutput,
+        event: wl_output::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_output::Event::Name { name } = event {
+            state.outputs.insert(toplevel_key(output), Some(name));
+        }
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for ToplevelData {
+    fn event(
+        _: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        _: <ZwlrForeignToplevelManagerV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {}
+
+    event_created_child!(ToplevelData, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for ToplevelData {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: WlrHandleEvent,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let id = toplevel_key(handle);
+        match event {
+            WlrHandleEvent::AppId { app_id } => state.pending(id).app_id = app_id,
+            WlrHandleEvent::Title { title } => state.pending(id).title = title,
+            WlrHandleEvent::State { state: states } => {
+                let states: Vec<u32> = states.chunks_exact(4)
+                    .map(|c| u32::from_ne_bytes([c[0], c[1], c[2], c[3]]))
+                    .collect();
+                let window = state.pending(id);
+                window.fullscreen = states.contains(&(WlrState::Fullscreen as u32));
+                window.focused = states.contains(&(WlrState::Activated as u32));
+            }
+            WlrHandleEvent::OutputEnter { output } => {
+                let name = state.output_name(&output);
+                state.pending(id).output = Some(name);
+            }
+            WlrHandleEvent::OutputLeave { output } => {
+                let name = state.output_name(&output);
+                let window = state.pending(id);
+                if window.output == Some(name) {
+                    window.output = None;
+                }
+            }
+            WlrHandleEvent::Done => state.done(id),
+            WlrHandleEvent::Closed => {
+                state.closed(&id);
+                handle.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ExtForeignToplevelListV1, ()> for ToplevelData {
+    fn event(
+        _: &mut Self,
+        _: &ExtForeignToplevelListV1,
+        _: <ExtForeignToplevelListV1 as Proxy>::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {}
+
+    event_created_child!(ToplevelData, ExtForeignToplevelListV1, [
+        ext_foreign_toplevel_list_v1::EVT_TOPLEVEL_OPCODE => (ExtForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ExtForeignToplevelHandleV1, ()> for ToplevelData {
+    fn event(
+        state: &mut Self,
+        handle: &ExtForeignToplevelHandleV1,
+        event: ExtHandleEvent,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let id = toplevel_key(handle);
+        match event {
+            ExtHandleEvent::AppId { app_id } => state.pending(id).app_id = app_id,
+            ExtHandleEvent::Title { title } => state.pending(id).title = title,
+            ExtHandleEvent::Done => state.done(id),
+            ExtHandleEvent::Closed => {
+                state.closed(&id);
+                handle.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+async fn run_toplevel(tracker: &WindowTracker) -> Result<(), IpcError> {
+    let conn = Connection::connect_to_env()?;
+    let mut event_queue = conn.new_event_queue();
+    let qh = event_queue.handle();
+
+    let mut data = ToplevelData::default();
+    let registry = conn.display().get_registry(&qh, ());
+    event_queue.roundtrip(&mut data)?;
+
+    // wlr first: it also reports fullscreen state and outputs
+    let protocol = if let Some((name, version)) = data.wlr_global {
+        registry.bind::<ZwlrForeignToplevelManagerV1, _, _>(name, version.min(3), &qh, ());
+        "zwlr_foreign_toplevel_manager_v1"
+    } else if let Some((name, _)) = data.ext_global {
+        registry.bind::<ExtForeignToplevelListV1, _, _>(name, 1, &qh, ());
+        "ext_foreign_toplevel_list_v1"
+    } else {
+        return Err(Unsupported("compositor lacks foreign-toplevel protocols").into());
+    };
+
+    // The initial toplevels arrive right after binding
+    event_queue.roundtrip(&mut data)?;
+    data.dirty = false;
+    tracker.replace(data.windows.clone()).await;
+    log_message(&format!("Tracking windows via {}", protocol));
+
+    let fd = watch_connection(&conn)?;
+    loop {
+        event_queue.dispatch_pending(&mut data)?;
+        if data.dirty {
+            data.dirty = false;
+            tracker.replace(data.windows.clone()).await;
+        }
+        match conn.flush() {
+            Err(WaylandError::Io(e)) if e.kind() == ErrorKind::WouldBlock => {}
+            result => result?,
+        }
+
+        // None means events are already queued, dispatch those first
+        let Some(guard) = event_queue.prepare_read() else {
+            continue;
+        };
+        let mut ready = fd.readable().await?;
+        match guard.read() {
+            Ok(_) => {}
+            Err(WaylandError::Io(e)) if e.kind() == ErrorKind::WouldBlock => ready.clear_ready(),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
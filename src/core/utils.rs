@@ -31,3 +31,44 @@ pub fn format_duration(dur: Duration) -> String {
         format!("{}h {}m", hours, minutes)
     }
 }
+
+/// Parse a human-friendly duration such as "90s", "5m", "1h30m" or "1h 30m".
+/// A bare number is taken as seconds.
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let input = input.trim().to_lowercase();
+    if input.is_empty() {
+        return None;
+    }
+    if let Ok(secs) = input.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+        } else if c.is_whitespace() {
+            continue;
+        } else {
+            let value: u64 = number.parse().ok()?;
+            let unit = match c {
+                'd' => 86400,
+                'h' => 3600,
+                'm' => 60,
+                's' => 1,
+                _ => return None,
+            };
+            total = total.checked_add(value.checked_mul(unit)?)?;
+            number.clear();
+        }
+    }
+
+    // Trailing digits without a unit ("1h30") are ambiguous
+    if !number.is_empty() {
+        return None;
+    }
+
+    Some(Duration::from_secs(total))
+}
@@ -1,14 +1,47 @@
 use std::{
     sync::Arc,
-    time::{Duration, Instant},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
-use tokio::sync::Mutex;
+use tokio::{sync::Mutex, time::sleep};
 
 use crate::{
-    core::manager::{helpers::{run_action, trigger_pre_suspend}, Manager},
-    log::log_message,
+    config,
+    core::{
+        manager::{
+            helpers::{apply_schedule, run_action, set_inhibitor, sync_logind_inhibitor, sync_profile_inhibitor, try_run_action, trigger_pre_suspend},
+            state::{is_inhibit_command, manual_inhibitor_source, Inhibitor, UNNAMED_INHIBIT},
+            Manager,
+        },
+        utils::format_duration,
+    },
+    log::{log_error_message, log_message},
 };
 
+/// Re-read the config from the source the daemon was started with and apply it.
+/// On failure the running config is left untouched.
+pub async fn reload_config(manager: &Arc<Mutex<Manager>>) -> Result<(), String> {
+    let source = manager.lock().await.state.config_source.clone();
+
+    match config::parser::load_config(&source) {
+        Ok(new_cfg) => {
+            let mut mgr = manager.lock().await;
+            mgr.state.update_from_config(&new_cfg).await;
+            apply_schedule(&mut mgr).await;
+            sync_profile_inhibitor(&mut mgr).await;
+            sync_logind_inhibitor(&mut mgr).await;
+            mgr.recheck_media().await;
+            mgr.trigger_instant_actions().await;
+
+            log_message("Config reloaded successfully");
+            Ok(())
+        }
+        Err(e) => {
+            log_error_message(&format!("Failed to reload config, keeping previous config: {:#}", e));
+            Err(format!("Failed to reload config: {e}"))
+        }
+    }
+}
+
 pub async fn trigger_action_by_name(manager: Arc<Mutex<Manager>>, name: &str) -> Result<String, String> {
     let normalized = name.replace('_', "-").to_lowercase();
     let mut mgr = manager.lock().await;
@@ -18,19 +51,13 @@ pub async fn trigger_action_by_name(manager: Arc<Mutex<Manager>>, name: &str) ->
         return Ok("pre_suspend".to_string());
     }
 
-    let block = if !mgr.state.ac_actions.is_empty() || !mgr.state.battery_actions.is_empty() {
-        match mgr.state.on_battery() {
-            Some(true) => &mgr.state.battery_actions,
-            Some(false) => &mgr.state.ac_actions,
-            None => &mgr.state.default_actions,
-        }
-    } else {
-        &mgr.state.default_actions
-    };
+    let block = mgr.state.get_active_actions();
 
     let action_opt = block.iter().find(|a| {
         let kind_name = format!("{:?}", a.kind).to_lowercase().replace('_', "-");
-        kind_name == normalized || a.name.to_lowercase() == normalized
+        kind_name == normalized
+            || a.kind.to_string().replace('_', "-") == normalized
+            || a.name.replace('_', "-").to_lowercase() == normalized
     });
 
     let action = match action_opt {
@@ -77,39 +104,49 @@ pub async fn trigger_action_by_name(manager: Arc<Mutex<Manager>>, name: &str) ->
                 let actions = &mut mgr.state.default_actions;
                 for a in actions.iter_mut() {
                     a.last_triggered = None;
+                    a.skipped = false;
                 }
             }
             {
                 let actions = &mut mgr.state.ac_actions;
                 for a in actions.iter_mut() {
                     a.last_triggered = None;
+                    a.skipped = false;
                 }
             }
             {
                 let actions = &mut mgr.state.battery_actions;
                 for a in actions.iter_mut() {
                     a.last_triggered = None;
+                    a.skipped = false;
                 }
             }
-
-            // Determine active block name first
-            let active_block = if !mgr.state.ac_actions.is_empty() || !mgr.state.battery_actions.is_empty() {
-                match mgr.state.on_battery() {
-                    Some(true) => "battery",
-                    Some(false) => "ac",
-                    None => "default",
+            {
+                let actions = &mut mgr.state.battery_low_actions;
+                for a in actions.iter_mut() {
+                    a.last_triggered = None;
+                    a.skipped = false;
                 }
-            } else {
-                "default"
-            };
+            }
+            {
+                let actions = &mut mgr.state.battery_critical_actions;
+                for a in actions.iter_mut() {
+                    a.last_triggered = None;
+                    a.skipped = false;
+                }
+            }
+            {
+                let actions = &mut mgr.state.profile_actions;
+                for a in actions.iter_mut() {
+                    a.last_triggered = None;
+                    a.skipped = false;
+                }
+            }
 
             // Now isolate block mutation
+            let mut advanced_past_lock = false;
             {
-                let actions = match active_block {
-                    "ac" => &mut mgr.state.ac_actions,
-                    "battery" => &mut mgr.state.battery_actions,
-                    _ => &mut mgr.state.default_actions,
-                };
+                let actions = mgr.state.get_active_actions_mut();
 
                 // Recalculate action index
                 let mut next_index = actions
@@ -129,18 +166,23 @@ pub async fn trigger_action_by_name(manager: Arc<Mutex<Manager>>, name: &str) ->
                             actions[next_index].last_triggered = Some(debounce_end);
                         }
 
-                        mgr.state.lock_state.post_advanced = true;
+                        advanced_past_lock = true;
                     }
                 }
 
                 mgr.state.action_index = next_index;
             }
+
+            if advanced_past_lock {
+                mgr.state.lock_state.post_advanced = true;
+            }
         }
 
         // Wake idle loop to recalculate timers
         mgr.state.notify.notify_one();
     } else {
-        run_action(&mut mgr, &action).await;
+        try_run_action(&mut mgr, &action).await
+            .map_err(|e| format!("Action '{}' failed: {}", action.name, e))?;
     }
 
     Ok(action.name)
@@ -150,7 +192,7 @@ pub async fn list_available_actions(manager: Arc<Mutex<Manager>>) -> Vec<String>
     let mgr = manager.lock().await;
     let mut actions = mgr
         .state
-        .default_actions
+        .get_active_actions()
         .iter()
         .map(|a| a.name.clone())
         .collect::<Vec<_>>();
@@ -162,3 +204,199 @@ pub async fn list_available_actions(manager: Arc<Mutex<Manager>>) -> Vec<String>
     actions.sort();
     actions
 }
+
+/// One line per configured profile, the active one marked with `*`
+pub async fn list_profiles(manager: Arc<Mutex<Manager>>) -> Vec<String> {
+    let mgr = manager.lock().await;
+    let Some(cfg) = &mgr.state.cfg else {
+        return Vec::new();
+    };
+
+    cfg.profiles
+        .iter()
+        .map(|p| {
+            let marker = if mgr.state.effective_profile() == Some(p.name.as_str()) { "*" } else { " " };
+            let mut details = Vec::new();
+            if !p.actions.is_empty() {
+                details.push(format!(
+                    "actions: {}",
+                    p.actions.iter().map(|a| a.name.as_str()).collect::<Vec<_>>().join(", ")
+                ));
+            }
+            if p.inhibit {
+                details.push("inhibits idle".to_string());
+            }
+            if let Some(media) = p.monitor_media {
+                details.push(format!("monitor_media: {}", media));
+            }
+            if let Some(apps) = &p.inhibit_apps {
+                details.push(format!("inhibit_apps: {}", apps.len()));
+            }
+
+            if details.is_empty() {
+                format!("{} {}", marker, p.name)
+            } else {
+                format!("{} {} ({})", marker, p.name, details.join("; "))
+            }
+        })
+        .collect()
+}
+
+/// Upper bound between expiry checks of a timed inhibit. Timers don't advance
+/// while suspended, so the wall clock is consulted again after each wait.
+const EXPIRY_CHECK: Duration = Duration::from_secs(60);
+
+/// Arguments of the `inhibit` IPC command, `[--for <secs>] [--reason <text>]`;
+/// the reason runs to the end of the line
+pub fn parse_inhibit_args(args: &str) -> Result<(Option<Duration>, Option<String>), String> {
+    let mut args = args.trim();
+
+    let mut duration = None;
+    if let Some(rest) = args.strip_prefix("--for ") {
+        let rest = rest.trim_start();
+        let (secs, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+        match secs.parse::<u64>() {
+            Ok(secs) if secs > 0 => duration = Some(Duration::from_secs(secs)),
+            _ => return Err(format!("Invalid inhibit duration '{}'", secs)),
+        }
+        args = rest.trim();
+    }
+
+    let reason = match args.strip_prefix("--reason ") {
+        Some(reason) if reason.trim() == UNNAMED_INHIBIT => {
+            return Err(format!("Inhibit reason '{}' is reserved", UNNAMED_INHIBIT));
+        }
+        Some(reason) if !reason.trim().is_empty() => Some(reason.trim().to_string()),
+        _ if args.is_empty() => None,
+        _ => return Err(format!("Invalid inhibit arguments '{}'", args)),
+    };
+    Ok((duration, reason))
+}
+
+/// Hold a manual inhibit, named by `reason` so several can coexist and be
+/// released separately, optionally lapsing after `duration`
+pub async fn add_manual_inhibit(
+    manager: Arc<Mutex<Manager>>,
+    reason: Option<&str>,
+    duration: Option<Duration>,
+) -> String {
+    let source = manual_inhibitor_source(reason);
+    let mut inhibitor = Inhibitor::all(reason.unwrap_or("manual inhibit"));
+    if let Some(duration) = duration {
+        inhibitor = inhibitor.expiring_after(duration);
+    }
+    let expires = inhibitor.expires;
+
+    {
+        let mut mgr = manager.lock().await;
+        set_inhibitor(&mut mgr, &source, Some(inhibitor)).await;
+    }
+
+    let label = reason.map(|r| format!(" ({})", r)).unwrap_or_default();
+    let Some(expires) = expires else {
+        return format!("Idle inhibited{} until `stasis uninhibit`", label);
+    };
+
+    tokio::spawn(async move {
+        loop {
+            let remaining = {
+                let mut mgr = manager.lock().await;
+                // Released, or replaced by another `stasis inhibit` with its own expiry
+                if mgr.state.inhibitors.get(&source).and_then(|i| i.expires) != Some(expires) {
+                    return;
+                }
+                match expires.duration_since(SystemTime::now()) {
+                    Ok(remaining) if !remaining.is_zero() => remaining,
+                    _ => {
+                        log_message(&format!("Manual inhibit {} expired", source));
+                        set_inhibitor(&mut mgr, &source, None).await;
+                        return;
+                    }
+                }
+            };
+            sleep(remaining.min(EXPIRY_CHECK)).await;
+        }
+    });
+
+    format!("Idle inhibited{} for {}", label, format_duration(duration.unwrap_or_default()))
+}
+
+/// Release the manual inhibit named `reason`, or every one held by `stasis inhibit`
+pub async fn release_manual_inhibit(manager: Arc<Mutex<Manager>>, reason: Option<&str>) -> Result<String, String> {
+    let mut mgr = manager.lock().await;
+
+    let sources: Vec<String> = match reason {
+        Some(reason) => {
+            let source = manual_inhibitor_source(Some(reason));
+            if reason == UNNAMED_INHIBIT || !mgr.state.is_inhibited_by(&source) {
+                return Err(format!("No manual inhibit named '{}'", reason));
+            }
+            vec![source]
+        }
+        None => mgr.state.inhibitors.keys().filter(|s| is_inhibit_command(s)).cloned().collect(),
+    };
+
+    if sources.is_empty() {
+        return Ok("No manual inhibits were active".to_string());
+    }
+    for source in &sources {
+        set_inhibitor(&mut mgr, source, None).await;
+    }
+    Ok(match reason {
+        Some(reason) => format!("Manual inhibit '{}' released", reason),
+        None => format!("Released {} manual inhibit(s)", sources.len()),
+    })
+}
+
+/// The inhibitor registry as JSON, for `inhibitors --json` and `info --json`
+pub fn inhibitors_json(inhibitors: &[(String, Inhibitor)]) -> serde_json::Value {
+    inhibitors
+        .iter()
+        .map(|(source, inhibitor)| {
+            serde_json::json!({
+                "source": source,
+                "reason": inhibitor.reason,
+                "blocks": inhibitor.scope.to_string(),
+                "since": inhibitor.since.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default(),
+                "active_for": inhibitor.since.elapsed().map(|d| d.as_secs()).unwrap_or_default(),
+                "expires_in": inhibitor.remaining().map(|d| d.as_secs()),
+            })
+        })
+        .collect()
+}
+
+/// One line per inhibitor currently held, e.g.
+/// `logind  systemd-inhibit: backup (blocks suspend, for 2m 3s)`, with the
+/// time left on timed inhibits
+pub async fn list_inhibitors(manager: Arc<Mutex<Manager>>, as_json: bool) -> String {
+    let inhibitors: Vec<(String, Inhibitor)> = {
+        let mgr = manager.lock().await;
+        mgr.state.inhibitors.iter().map(|(s, i)| (s.clone(), i.clone())).collect()
+    };
+
+    if as_json {
+        return inhibitors_json(&inhibitors).to_string();
+    }
+    if inhibitors.is_empty() {
+        return "Nothing is inhibiting idle".to_string();
+    }
+
+    let width = inhibitors.iter().map(|(source, _)| source.len()).max().unwrap_or(0);
+    inhibitors
+        .iter()
+        .map(|(source, inhibitor)| {
+            let left = inhibitor.remaining()
+                .map(|d| format!(", {} left", format_duration(d)))
+                .unwrap_or_default();
+            format!(
+                "{:<width$}  {} (blocks {}, for {}{})",
+                source,
+                inhibitor.reason,
+                inhibitor.scope,
+                format_duration(inhibitor.since.elapsed().unwrap_or_default()),
+                left,
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
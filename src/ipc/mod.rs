@@ -8,12 +8,16 @@ use tokio::{
 };
 
 use crate::{
-    config, core::{
-        manager::{helpers::{get_manual_inhibit, set_manual_inhibit, trigger_all_idle_actions}, Manager}, 
+    config::info::InfoStatus,
+    core::{
+        manager::{helpers::{get_manual_inhibit, set_active_profile, set_manual_inhibit, trigger_all_idle_actions}, Manager}, 
         services::app_inhibit::AppInhibitor,
         utils::format_duration,
     }, 
-    ipc::commands::trigger_action_by_name, 
+    ipc::commands::{
+        add_manual_inhibit, inhibitors_json, list_inhibitors, list_profiles, parse_inhibit_args, release_manual_inhibit,
+        reload_config, trigger_action_by_name,
+    }, 
     log::{log_error_message, log_message}, 
     SOCKET_PATH
 };
@@ -45,22 +49,9 @@ pub async fn spawn_ipc_socket_with_listener(
                                     let response = match cmd.as_str() {
                                         // === CONFIG ===
                                         "reload" => {
-                                            match config::parser::load_config() {
-                                                Ok(new_cfg) => {
-                                                    let mut mgr = manager.lock().await;
-                                                    mgr.state.update_from_config(&new_cfg).await;
-                                                    mgr.recheck_media().await;
-                                                    mgr.trigger_instant_actions().await;
-                                                    
-                                                    drop(mgr);
-
-                                                    log_message("Config reloaded successfully");
-                                                    "Config reloaded successfully".to_string()
-                                                }
-                                                Err(e) => {
-                                                    log_error_message(&format!("Failed to reload config: {}", e));
-                                                    format!("ERROR: Failed to reload config: {e}")
-                                                }
+                                            match reload_config(&manager).await {
+                                                Ok(()) => "Config reloaded successfully".to_string(),
+                                                Err(e) => format!("ERROR: {e}"),
                                             }
                                         }
 
@@ -95,6 +86,36 @@ pub async fn spawn_ipc_socket_with_listener(
                                             }
                                         }
 
+                                        // === PROFILES ===
+                                        "profile list" => {
+                                            match list_profiles(manager.clone()).await.as_slice() {
+                                                [] => "No profiles configured".to_string(),
+                                                profiles => profiles.join("\n"),
+                                            }
+                                        }
+
+                                        "profile reset" => {
+                                            let mut mgr = manager.lock().await;
+                                            match set_active_profile(&mut mgr, None).await {
+                                                Ok(()) => "Manual profile cleared".to_string(),
+                                                Err(e) => format!("ERROR: {e}"),
+                                            }
+                                        }
+
+                                        cmd if cmd.starts_with("profile set ") => {
+                                            let name = cmd.strip_prefix("profile set ").unwrap_or("").trim();
+
+                                            if name.is_empty() {
+                                                "ERROR: No profile name provided".to_string()
+                                            } else {
+                                                let mut mgr = manager.lock().await;
+                                                match set_active_profile(&mut mgr, Some(name)).await {
+                                                    Ok(()) => format!("Profile '{}' activated", name),
+                                                    Err(e) => format!("ERROR: {e}"),
+                                                }
+                                            }
+                                        }
+
                                         "stop" => {
                                             log_message("Received stop command — shutting down gracefully");
                                             let manager_clone = Arc::clone(&manager);
@@ -149,9 +170,19 @@ pub async fn spawn_ipc_socket_with_listener(
                                                     Ok(mgr) => {
                                                         let idle_time = mgr.state.last_activity.elapsed();
                                                         let uptime = mgr.state.start_time.elapsed();
-                                                        let manually_inhibited = mgr.state.manually_paused;
+                                                        let manually_inhibited = mgr.state.manually_paused();
                                                         let paused = mgr.state.paused;
                                                         let cfg_clone = mgr.state.cfg.clone();
+                                                        let active_profile = mgr.state.effective_profile().map(str::to_string);
+                                                        let schedule_active = mgr.state.schedule_active.clone();
+                                                        let current_block = mgr.state.current_block.clone();
+                                                        let battery = mgr.state.battery_capacity().zip(mgr.state.battery_level());
+                                                        let logind_inhibitors = mgr.state.logind_inhibitors.clone();
+                                                        let wayland_inhibited = mgr.state.is_inhibited_by("wayland");
+                                                        let inhibitors: Vec<_> = mgr.state.inhibitors
+                                                            .iter()
+                                                            .map(|(s, i)| (s.clone(), i.clone()))
+                                                            .collect();
                                                         
                                                         // Release manager lock before acquiring app_inhibitor lock
                                                         drop(mgr);
@@ -162,6 +193,7 @@ pub async fn spawn_ipc_socket_with_listener(
                                                             async {
                                                                 let mut inhibitor = app_inhibitor.lock().await;
                                                                 inhibitor.is_any_app_running().await
+                                                                    || inhibitor.fullscreen_window().is_some()
                                                             }
                                                         ).await {
                                                             Ok(result) => result,
@@ -179,22 +211,72 @@ pub async fn spawn_ipc_socket_with_listener(
                                                                 "idle_active"
                                                             };
 
+                                                            let schedule = if schedule_active.is_empty() {
+                                                                "none".to_string()
+                                                            } else {
+                                                                schedule_active.join(", ")
+                                                            };
+
+                                                            let logind_holders = logind_inhibitors
+                                                                .iter()
+                                                                .map(|i| format!("{} ({})", i.who, i.why))
+                                                                .collect::<Vec<_>>();
+
                                                             serde_json::json!({
                                                                 "text": "",
                                                                 "alt": icon,
                                                                 "tooltip": format!(
-                                                                    "{}\nIdle time: {}\nUptime: {}\nPaused: {}\nManually paused: {}\nApp blocking: {}",
+                                                                    "{}\nBlock: {}{}\nProfile: {}\nSchedule: {}\nIdle time: {}\nUptime: {}\nPaused: {}\nManually paused: {}\nApp blocking: {}\nCompositor inhibiting: {}\nLogind inhibitors: {}\nInhibitors: {}",
                                                                     if idle_inhibited { "Idle inhibited" } else { "Idle active" },
+                                                                    current_block,
+                                                                    battery.map(|(c, l)| format!(" (battery {}%, {})", c, l)).unwrap_or_default(),
+                                                                    active_profile.as_deref().unwrap_or("none"),
+                                                                    schedule,
                                                                     format_duration(idle_time),
                                                                     format_duration(uptime),
                                                                     paused,
                                                                     manually_inhibited,
-                                                                    app_blocking
-                                                                )
+                                                                    app_blocking,
+                                                                    wayland_inhibited,
+                                                                    if logind_holders.is_empty() { "none".to_string() } else { logind_holders.join(", ") },
+                                                                    if inhibitors.is_empty() {
+                                                                        "none".to_string()
+                                                                    } else {
+                                                                        inhibitors.iter()
+                                                                            .map(|(s, i)| match i.remaining() {
+                                                                                Some(left) => format!("{} ({} left)", s, format_duration(left)),
+                                                                                None => s.clone(),
+                                                                            })
+                                                                            .collect::<Vec<_>>()
+                                                                            .join(", ")
+                                                                    }
+                                                                ),
+                                                                "block": current_block,
+                                                                "battery": battery.map(|(c, _)| c),
+                                                                "profile": active_profile,
+                                                                "schedule": schedule_active,
+                                                                "wayland_inhibited": wayland_inhibited,
+                                                                "logind_inhibitors": logind_inhibitors
+                                                                    .iter()
+                                                                    .map(|i| serde_json::json!({ "who": i.who, "why": i.why, "pid": i.pid }))
+                                                                    .collect::<Vec<_>>(),
+                                                                "inhibitors": inhibitors_json(&inhibitors)
                                                             })
                                                             .to_string()
                                                         } else if let Some(cfg) = &cfg_clone {
-                                                            cfg.pretty_print(Some(idle_time), Some(uptime), Some(idle_inhibited), Some(manually_inhibited))
+                                                            cfg.pretty_print(&InfoStatus {
+                                                                idle_time: Some(idle_time),
+                                                                uptime: Some(uptime),
+                                                                is_inhibited: Some(idle_inhibited),
+                                                                is_manually_inhibited: Some(manually_inhibited),
+                                                                active_block: Some(&current_block),
+                                                                active_profile: active_profile.as_deref(),
+                                                                active_schedule: &schedule_active,
+                                                                battery,
+                                                                logind_inhibitors: &logind_inhibitors,
+                                                                wayland_inhibited: Some(wayland_inhibited),
+                                                                inhibitors: &inhibitors,
+                                                            })
                                                         } else {
                                                             "No configuration loaded".to_string()
                                                         };
@@ -220,6 +302,28 @@ pub async fn spawn_ipc_socket_with_listener(
                                             }
                                         }
 
+                                        cmd if cmd == "inhibit" || cmd.starts_with("inhibit ") => {
+                                            match parse_inhibit_args(cmd.strip_prefix("inhibit").unwrap_or("")) {
+                                                Ok((duration, reason)) => {
+                                                    add_manual_inhibit(manager.clone(), reason.as_deref(), duration).await
+                                                }
+                                                Err(e) => format!("ERROR: {e}"),
+                                            }
+                                        }
+
+                                        cmd if cmd == "uninhibit" || cmd.starts_with("uninhibit ") => {
+                                            let reason = cmd.strip_prefix("uninhibit").unwrap_or("").trim();
+                                            let reason = (!reason.is_empty()).then_some(reason);
+                                            match release_manual_inhibit(manager.clone(), reason).await {
+                                                Ok(msg) => msg,
+                                                Err(e) => format!("ERROR: {e}"),
+                                            }
+                                        }
+
+                                        "inhibitors" | "inhibitors --json" => {
+                                            list_inhibitors(manager.clone(), cmd.contains("--json")).await
+                                        }
+
                                         "list_actions" => {
                                             match crate::ipc::commands::list_available_actions(manager.clone()).await.as_slice() {
                                                 [] => "No actions available".to_string(),
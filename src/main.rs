@@ -10,24 +10,27 @@ use eyre::Result;
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
     net::{UnixListener, UnixStream}, 
-    sync::Mutex,
+    sync::{Mutex, Notify},
     time::{Duration, timeout},
     task::LocalSet
 };
 
 use crate::{
-    cli::Command,
-    config::parser::load_config,
+    cli::{Command, ProfileCommand},
+    config::{check::run_check, parser::load_config, ConfigSource},
     core::{
         manager::{idle_loops::{spawn_idle_task, spawn_lock_watcher}, Manager}, 
         services::{
             app_inhibit::{AppInhibitor, spawn_app_inhibit_task},
+            config_watcher::spawn_config_watcher,
             dbus::listen_for_power_events, 
-            input::spawn_input_task,
             media::spawn_media_monitor_dbus,
             power_detection::spawn_power_source_monitor,
-            wayland::{setup as setup_wayland},
-        }
+            schedule::spawn_schedule_task,
+            screensaver::spawn_screensaver_service,
+            wayland::{setup as setup_wayland, sync_input_fallback},
+        },
+        utils::{detect_chassis, ChassisKind},
     },
 };
 
@@ -41,6 +44,17 @@ const SOCKET_PATH: &str = "/tmp/stasis.sock";
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<()> {
     let args = Args::parse();
+
+    // --- Config check runs standalone (no daemon or Wayland needed) ---
+    if let Some(Command::Check { file, chassis }) = &args.command {
+        let source = ConfigSource::from_cli(file.clone().or_else(|| args.config.clone()));
+        let chassis = match chassis.as_deref() {
+            Some("laptop") => ChassisKind::Laptop,
+            Some(_) => ChassisKind::Desktop,
+            None => detect_chassis(),
+        };
+        exit(run_check(&source, chassis));
+    }
     
     if var("WAYLAND_DISPLAY").is_err() {
         eprintln!("Warn: Stasis requires wayland to run.");
@@ -116,6 +130,75 @@ async fn main() -> Result<()> {
                 }
             }
             
+            Command::Profile { .. } | Command::Inhibit { .. } | Command::Uninhibit { .. } => {
+                let msg = match cmd {
+                    Command::Profile { action } => match action {
+                        ProfileCommand::Set { name } => format!("profile set {}", name),
+                        ProfileCommand::List => "profile list".to_string(),
+                        ProfileCommand::Reset => "profile reset".to_string(),
+                    },
+                    Command::Inhibit { duration, reason } => {
+                        let mut msg = "inhibit".to_string();
+                        if let Some(duration) = duration {
+                            msg.push_str(&format!(" --for {}", duration.as_secs().max(1)));
+                        }
+                        if let Some(reason) = reason {
+                            msg.push_str(&format!(" --reason {}", reason));
+                        }
+                        msg
+                    }
+                    Command::Uninhibit { reason } => match reason {
+                        Some(reason) => format!("uninhibit {}", reason),
+                        None => "uninhibit".to_string(),
+                    },
+                    _ => unreachable!(),
+                };
+
+                match timeout(Duration::from_secs(3), UnixStream::connect(SOCKET_PATH)).await {
+                    Ok(Ok(mut stream)) => {
+                        let _ = stream.write_all(msg.as_bytes()).await;
+
+                        let mut response = Vec::new();
+                        match timeout(Duration::from_secs(3), stream.read_to_end(&mut response)).await {
+                            Ok(Ok(_)) => {
+                                let response_text = String::from_utf8_lossy(&response);
+                                if response_text.starts_with("ERROR:") {
+                                    eprintln!("{}", response_text.trim_start_matches("ERROR:").trim());
+                                    std::process::exit(1);
+                                }
+                                println!("{}", response_text);
+                            }
+                            Ok(Err(e)) => eprintln!("Failed to read response: {}", e),
+                            Err(_) => eprintln!("Timeout reading response"),
+                        }
+                    }
+                    Ok(Err(_)) | Err(_) => {
+                        eprintln!("No running Stasis instance found");
+                        std::process::exit(1);
+                    }
+                }
+            }
+
+            Command::Inhibitors { json } => {
+                match timeout(Duration::from_secs(3), UnixStream::connect(SOCKET_PATH)).await {
+                    Ok(Ok(mut stream)) => {
+                        let msg = if *json { "inhibitors --json" } else { "inhibitors" };
+                        let _ = stream.write_all(msg.as_bytes()).await;
+
+                        let mut response = Vec::new();
+                        match timeout(Duration::from_secs(2), stream.read_to_end(&mut response)).await {
+                            Ok(Ok(_)) => println!("{}", String::from_utf8_lossy(&response)),
+                            Ok(Err(e)) => eprintln!("Failed to read response: {}", e),
+                            Err(_) => eprintln!("Timeout reading response"),
+                        }
+                    }
+                    Ok(Err(_)) | Err(_) => {
+                        eprintln!("No running Stasis instance found");
+                        std::process::exit(1);
+                    }
+                }
+            }
+
             Command::ListActions => {
                 match timeout(Duration::from_secs(3), UnixStream::connect(SOCKET_PATH)).await {
                     Ok(Ok(mut stream)) => {
@@ -203,30 +286,43 @@ async fn main() -> Result<()> {
         log_message("Verbose mode enabled");
         set_verbose(true);
     }
-    let cfg = Arc::new(load_config()?);
-    let manager = Manager::new(Arc::clone(&cfg));
+    let config_source = ConfigSource::from_cli(args.config.clone());
+    let cfg = Arc::new(load_config(&config_source)?);
+
+    // --- One system bus connection, shared by the D-Bus listeners and logind actions ---
+    let system_bus = match zbus::Connection::system().await {
+        Ok(connection) => Some(connection),
+        Err(e) => {
+            log_error_message(&format!("Failed to connect to the system bus: {}", e));
+            None
+        }
+    };
+
+    let mut manager = Manager::new(Arc::clone(&cfg));
+    manager.state.config_source = config_source;
+    manager.state.system_bus = system_bus.clone();
     let manager = Arc::new(Mutex::new(manager));
 
     // --- Spawn background tasks ---
     let idle_handle = spawn_idle_task(Arc::clone(&manager));
     let lock_handle = spawn_lock_watcher(Arc::clone(&manager)).await;
-    let input_handle = spawn_input_task(Arc::clone(&manager));
     
     // Store handles in manager
     {
         let mut mgr = manager.lock().await;
         mgr.idle_task_handle = Some(idle_handle);
         mgr.lock_task_handle = Some(lock_handle);
-        mgr.input_task_handle = Some(input_handle);
     } 
     
     // --- Spawn suspend event listener ---
-    let dbus_manager = Arc::clone(&manager);
-    tokio::spawn(async move {
-        if let Err(e) = listen_for_power_events(dbus_manager).await {
-            log_error_message(&format!("D-Bus suspend event listener failed: {}", e));
-        }
-    });
+    if let Some(connection) = system_bus.clone() {
+        let dbus_manager = Arc::clone(&manager);
+        tokio::spawn(async move {
+            if let Err(e) = listen_for_power_events(dbus_manager, connection).await {
+                log_error_message(&format!("D-Bus suspend event listener failed: {}", e));
+            }
+        });
+    }
 
     // --- AC/Battery Detection (DETECT FIRST, synchronously) ---
     {
@@ -236,7 +332,10 @@ async fn main() -> Result<()> {
     
     // --- AC/Battery Detection ---
     let laptop_manager = Arc::clone(&manager);
-    tokio::spawn(spawn_power_source_monitor(laptop_manager));
+    tokio::spawn(spawn_power_source_monitor(laptop_manager, system_bus.clone()));
+
+    // --- Time-of-day schedule (before instants, it may select the block) ---
+    spawn_schedule_task(Arc::clone(&manager)).await;
 
     // Immediately trigger instants at startup
     {
@@ -251,15 +350,24 @@ async fn main() -> Result<()> {
     ).await;
    
     // --- Spawn media monitor task ---
-    if cfg.monitor_media {
+    // Also needed when only a profile turns media monitoring on
+    if cfg.monitor_media || cfg.profiles.iter().any(|p| p.monitor_media == Some(true)) {
         if let Err(e) = spawn_media_monitor_dbus(Arc::clone(&manager)).await {
             log_error_message(&format!("Failed to spawn media monitor: {}", e));
         }
     }
+
+    // --- org.freedesktop.ScreenSaver inhibition ---
+    if let Err(e) = spawn_screensaver_service(Arc::clone(&manager)).await {
+        log_error_message(&format!("Failed to serve org.freedesktop.ScreenSaver: {}", e));
+    }
     
     // --- Wayland setup ---
     let wayland_manager = Arc::clone(&manager);
-    let _ = setup_wayland(wayland_manager, cfg.respect_wayland_inhibitors).await?;
+    let compositor_gone = setup_wayland(wayland_manager, cfg.respect_wayland_inhibitors).await?;
+
+    // --- libinput, only needed when the compositor can't report idle stages ---
+    sync_input_fallback(&manager).await;
 
     // -- IPC Control Socket ---
     ipc::spawn_ipc_socket_with_listener(
@@ -268,15 +376,19 @@ async fn main() -> Result<()> {
         listener,
     ).await;
 
+    // --- Config hot-reload ---
+    spawn_config_watcher(Arc::clone(&manager)).await;
+
     setup_shutdown_handler(
         Arc::clone(&manager),
         Arc::clone(&app_inhibitor),
     ).await;
 
-   // Monitor Wayland compositor connection
+    // Exit along with the compositor if exit_with_compositor is set
     spawn_wayland_monitor(
         Arc::clone(&manager),
         Arc::clone(&app_inhibitor),
+        compositor_gone,
     ).await;
     
     // --- Log startup message ---
@@ -334,34 +446,20 @@ async fn setup_shutdown_handler(
 async fn spawn_wayland_monitor(
     manager: Arc<Mutex<Manager>>,
     app_inhibitor: Arc<Mutex<AppInhibitor>>,
+    compositor_gone: Arc<Notify>,
 ) {
-    // Capture env vars once
-    let wayland_display = match std::env::var("WAYLAND_DISPLAY") {
-        Ok(display) => display,
-        Err(_) => return,
-    };
-    let xdg_runtime = std::env::var("XDG_RUNTIME_DIR").unwrap_or_else(|_| "/run/user/1000".to_string());
-    let socket_path = format!("{}/{}", xdg_runtime, wayland_display);
-
     tokio::spawn(async move {
-        loop {
-            tokio::time::sleep(Duration::from_secs(2)).await;
+        compositor_gone.notified().await;
+        log_message("Wayland compositor is gone, shutting down...");
 
-            // Try connecting to the Wayland socket
-            if UnixStream::connect(&socket_path).await.is_err() {
-                log_message("Wayland compositor is no longer responding, shutting down...");
+        // Shutdown idle timer
+        manager.lock().await.shutdown().await;
 
-                // Shutdown idle timer
-                manager.lock().await.shutdown().await;
+        // Shutdown app inhibitor
+        app_inhibitor.lock().await.shutdown().await;
 
-                // Shutdown app inhibitor
-                app_inhibitor.lock().await.shutdown().await;
-
-                let _ = std::fs::remove_file(SOCKET_PATH);
-                log_message("Shutdown complete, goodbye!");
-                std::process::exit(0);
-            }
-        }
+        let _ = std::fs::remove_file(SOCKET_PATH);
+        log_message("Shutdown complete, goodbye!");
+        std::process::exit(0);
     });
 }
-